@@ -0,0 +1,328 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{collections::BTreeMap, error::Error, slice};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::CommandRule;
+
+/// `Aliases` are the named sets of the configuration, a rule reference them by their name.
+/// The name of an alias is in uppercase, like `WEB_ADMINS`, so it can't be mistaken for a user,
+/// a host or a command, and an alias can contain other aliases
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Aliases {
+    /// The sets of commands
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, Vec<CommandRule>>,
+    /// The sets of users that invoke Rudo
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<String, Vec<String>>,
+    /// The sets of users to impersonate
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runas: BTreeMap<String, Vec<String>>,
+    /// The sets of hosts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, Vec<String>>,
+}
+
+impl Aliases {
+    /// Function to know if there is no alias, so the section isn't written in the configuration file
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+            && self.users.is_empty()
+            && self.runas.is_empty()
+            && self.hosts.is_empty()
+    }
+    /// Function to verify that every alias has a valid name, and that its members can be expanded
+    ///
+    /// # Errors
+    /// Return an error if the name of an alias isn't in uppercase, if an alias isn't defined, if there is a cycle
+    /// or if a reference to a commands alias has arguments or a digest
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for (name, rules) in &self.commands {
+            verify_name("commands", name)?;
+            verify_command_references(rules)?;
+            for rule in self.expand_commands(&[alias_rule(name)])? {
+                rule.validate()?;
+            }
+        }
+        for (kind, section) in &[
+            ("users", &self.users),
+            ("runas", &self.runas),
+            ("hosts", &self.hosts),
+        ] {
+            for name in section.keys() {
+                verify_name(kind, name)?;
+                names(kind, section).expand(slice::from_ref(name))?;
+            }
+        }
+        Ok(())
+    }
+    /// Function to replace the command aliases by their commands, a rule whose path is an alias name is a reference
+    ///
+    /// # Errors
+    /// Return an error if an alias isn't defined, or if there is a cycle
+    pub fn expand_commands(
+        &self,
+        rules: &[CommandRule],
+    ) -> Result<Vec<CommandRule>, Box<dyn Error>> {
+        Expander {
+            kind: "commands",
+            section: &self.commands,
+            alias_of: |rule: &CommandRule| {
+                Some(rule.path.as_str()).filter(|path| is_alias_name(path))
+            },
+        }
+        .expand(rules)
+    }
+    /// Function to replace the user aliases by their users
+    ///
+    /// # Errors
+    /// Return an error if an alias isn't defined, or if there is a cycle
+    pub fn expand_users(&self, users: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+        names("users", &self.users).expand(users)
+    }
+    /// Function to know if the username of a rule, a user or a users alias, include the user.
+    /// An invalid alias include no one, the configuration file is refused before
+    #[must_use]
+    pub fn user_matches(&self, username: &str, user: &str) -> bool {
+        self.expand_users(&[username.to_owned()])
+            .map_or(false, |users| users.iter().any(|name| name == user))
+    }
+    /// Function to replace the runas aliases by the users to impersonate
+    ///
+    /// # Errors
+    /// Return an error if an alias isn't defined, or if there is a cycle
+    pub fn expand_runas(&self, users: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+        names("runas", &self.runas).expand(users)
+    }
+    /// Function to replace the host aliases by their hosts
+    ///
+    /// # Errors
+    /// Return an error if an alias isn't defined, or if there is a cycle
+    pub fn expand_hosts(&self, hosts: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+        names("hosts", &self.hosts).expand(hosts)
+    }
+}
+
+/// Function to know if a name is the one of an alias: an uppercase letter, then uppercase letters, digits or `_`
+#[must_use]
+pub fn is_alias_name(name: &str) -> bool {
+    let mut characters = name.chars();
+    characters
+        .next()
+        .map_or(false, |first| first.is_ascii_uppercase())
+        && characters.all(|character| {
+            character.is_ascii_uppercase() || character.is_ascii_digit() || character == '_'
+        })
+}
+
+/// Function to verify that the name of an alias is in uppercase, or it could never be referenced
+fn verify_name(kind: &str, name: &str) -> Result<(), Box<dyn Error>> {
+    if is_alias_name(name) {
+        Ok(())
+    } else {
+        Err(From::from(format!(
+            "the name of the {} alias {} must be uppercase letters, digits and _",
+            kind, name
+        )))
+    }
+}
+
+/// Function to verify that a rule referencing a commands alias has no arguments and no digest,
+/// they would be lost when the alias is replaced by its own rules
+///
+/// # Errors
+/// Return an error if a reference has arguments or a digest
+pub(crate) fn verify_command_references(rules: &[CommandRule]) -> Result<(), Box<dyn Error>> {
+    for rule in rules {
+        if is_alias_name(&rule.path) && (rule.args.is_some() || rule.digest.is_some()) {
+            return Err(From::from(format!(
+                "the commands alias {} can't have args or a digest, they belong to its rules",
+                rule.path
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Function to create the reference to a command alias, as written in a rule
+fn alias_rule(name: &str) -> CommandRule {
+    CommandRule {
+        path: name.to_owned(),
+        args: None,
+        digest: None,
+    }
+}
+
+/// Function to create the expander of a section of names, where a member is an alias if its name is one
+fn names<'a>(kind: &'a str, section: &'a BTreeMap<String, Vec<String>>) -> Expander<'a, String> {
+    Expander {
+        kind,
+        section,
+        alias_of: |name| Some(name.as_str()).filter(|alias| is_alias_name(alias)),
+    }
+}
+
+/// `Expander` replace the aliases of a section by their members, recursively
+struct Expander<'a, T> {
+    /// The name of the section, for the errors
+    kind: &'a str,
+    /// The aliases of the section
+    section: &'a BTreeMap<String, Vec<T>>,
+    /// Give the name of the alias a member reference, if it's a reference
+    alias_of: fn(&T) -> Option<&str>,
+}
+
+impl<T: Clone> Expander<'_, T> {
+    /// Expand the members, an alias is replaced by its members
+    fn expand(&self, members: &[T]) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut expanded = Vec::new();
+        self.expand_into(members, &mut Vec::new(), &mut expanded)?;
+        Ok(expanded)
+    }
+    /// Expand the members in the vector, the stack is the aliases being expanded to find a cycle
+    fn expand_into(
+        &self,
+        members: &[T],
+        stack: &mut Vec<String>,
+        expanded: &mut Vec<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        for member in members {
+            if let Some(name) = (self.alias_of)(member) {
+                if stack.iter().any(|alias| alias == name) {
+                    return Err(From::from(format!(
+                        "the {} alias {} is in a cycle: {} -> {}",
+                        self.kind,
+                        name,
+                        stack.join(" -> "),
+                        name
+                    )));
+                }
+                let alias_members = self
+                    .section
+                    .get(name)
+                    .ok_or_else(|| format!("the {} alias {} isn't defined", self.kind, name))?;
+                stack.push(name.to_owned());
+                self.expand_into(alias_members, stack, expanded)?;
+                stack.pop();
+            } else {
+                expanded.push(member.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::{is_alias_name, Aliases};
+    use crate::config::CommandRule;
+
+    /// Create the aliases of the tests
+    fn test_aliases() -> Result<Aliases, Box<dyn Error>> {
+        let data = "commands:\n  WEB:\n    - path: /usr/bin/systemctl\n      args: [restart, nginx]\n    - path: TOOLS\n  TOOLS:\n    - path: /usr/bin/uptime\nusers:\n  ADMINS: [alice, OPS]\n  OPS: [bob]\nrunas:\n  SERVICES: [www-data]\nhosts:\n  WEB_HOSTS: [web1, web2]\n";
+        Ok(serde_yaml::from_str(data)?)
+    }
+
+    #[test]
+    fn test_is_alias_name() -> Result<(), Box<dyn Error>> {
+        if is_alias_name("WEB_2")
+            && !is_alias_name("alice")
+            && !is_alias_name("2WEB")
+            && !is_alias_name("/usr/bin/id")
+            && !is_alias_name("")
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to recognize the name of an alias"))
+        }
+    }
+
+    #[test]
+    fn test_expand() -> Result<(), Box<dyn Error>> {
+        let aliases = test_aliases()?;
+        aliases.validate()?;
+        let users = aliases.expand_users(&[String::from("ADMINS"), String::from("carol")])?;
+        let commands = aliases.expand_commands(&[CommandRule {
+            path: String::from("WEB"),
+            args: None,
+            digest: None,
+        }])?;
+        let paths: Vec<&str> = commands.iter().map(|rule| rule.path.as_str()).collect();
+        if users == vec!["alice", "bob", "carol"]
+            && paths == vec!["/usr/bin/systemctl", "/usr/bin/uptime"]
+            && aliases.expand_hosts(&[String::from("WEB_HOSTS")])? == vec!["web1", "web2"]
+            && aliases.expand_runas(&[String::from("SERVICES")])? == vec!["www-data"]
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to expand the aliases"))
+        }
+    }
+
+    #[test]
+    fn test_cycle() -> Result<(), Box<dyn Error>> {
+        let mut aliases = test_aliases()?;
+        aliases
+            .users
+            .insert(String::from("OPS"), vec![String::from("ADMINS")]);
+        match aliases.validate() {
+            Err(err) if err.to_string().contains("ADMINS -> OPS -> ADMINS") => Ok(()),
+            _ => Err(From::from("Test failed to find the cycle of the aliases")),
+        }
+    }
+
+    #[test]
+    fn test_reference_with_args() -> Result<(), Box<dyn Error>> {
+        let mut aliases = test_aliases()?;
+        aliases.commands.insert(
+            String::from("RESTART"),
+            vec![CommandRule {
+                path: String::from("WEB"),
+                args: Some(vec![String::from("stop")]),
+                digest: None,
+            }],
+        );
+        match aliases.validate() {
+            Err(err) if err.to_string().contains("WEB can't have args") => Ok(()),
+            _ => Err(From::from(
+                "Test failed to refuse the arguments of a reference to an alias",
+            )),
+        }
+    }
+
+    #[test]
+    fn test_undefined_and_invalid_name() -> Result<(), Box<dyn Error>> {
+        let mut aliases = test_aliases()?;
+        let undefined = aliases.expand_runas(&[String::from("NOBODY")]);
+        aliases
+            .hosts
+            .insert(String::from("db_hosts"), vec![String::from("db1")]);
+        let undefined_ok = undefined.map_err(|err| err.to_string()).err()
+            == Some(String::from("the runas alias NOBODY isn't defined"));
+        if undefined_ok && aliases.validate().is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to refuse the invalid aliases"))
+        }
+    }
+}
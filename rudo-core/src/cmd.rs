@@ -0,0 +1,500 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    ffi::{CString, OsStr, OsString},
+    fs::{self, File},
+    io::{self, ErrorKind},
+    iter,
+    os::raw::c_char,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::PermissionsExt,
+        io::{AsRawFd, RawFd},
+        process::{CommandExt, ExitStatusExt},
+    },
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    ptr,
+};
+
+use log::debug;
+use sha2::{Digest, Sha256};
+
+use crate::{config::SHA256_PREFIX, error::RudoError, journal, policy::EnvRules, utils};
+
+/// Structure to keep the result of the extraction of the command give in the command-line interface
+#[derive(Debug)]
+pub struct CmdData<'a> {
+    /// Name of the program
+    pub program: String,
+    /// The arguments of the program
+    pub args: Vec<&'a str>,
+    /// The sha256 digest the program must have, it's then executed from a sealed copy.
+    /// A program with a digest is refused off Linux, that has no `memfd_create`
+    pub digest: Option<String>,
+}
+
+impl<'a> CmdData<'a> {
+    /// Create the new Command with the command supply by the user with the command-line interface
+    ///
+    /// # Errors
+    /// Return an error if the command is empty
+    pub fn new(mut command: Vec<&'a str>) -> Result<Self, Box<dyn Error>> {
+        let mut program = String::new();
+        // Extract the first word then remove it after verifying its existence
+        debug!("Extract the first word then remove it after verifying its existence");
+        let data = match command.get(0) {
+            Some(data) => data,
+            None => {
+                return Err(From::from(
+                    "Command is empty! Please give Rudo something to launch",
+                ))
+            }
+        };
+        program.push_str(data);
+        command.remove(0);
+        // Copy the rest of the value and return it
+        let args = command;
+        debug!("Return the new Command structure");
+        Ok(Self {
+            program,
+            args,
+            digest: None,
+        })
+    }
+}
+
+/// Function to find the absolute path of the program, so the policy match the program that is run.
+/// A name is searched in the absolute directories of `PATH`, and a path is relative to the working directory
+///
+/// # Errors
+/// Return a `CommandNotFound` error if the program isn't found, or an error if its path isn't valid UTF-8
+pub fn resolve_program(
+    program: &str,
+    path: Option<&OsStr>,
+    cwd: &Path,
+) -> Result<String, Box<dyn Error>> {
+    let found = if program.contains('/') {
+        Some(cwd.join(program))
+    } else {
+        path.into_iter()
+            .flat_map(env::split_paths)
+            .filter(|dir| dir.is_absolute())
+            .map(|dir| dir.join(program))
+            .find(is_executable)
+    };
+    match found {
+        Some(path_buf) => match path_buf.into_os_string().into_string() {
+            Ok(resolved) => {
+                debug!("{} is {}", program, resolved);
+                Ok(resolved)
+            }
+            Err(_) => Err(From::from(format!(
+                "The path of {} isn't valid UTF-8",
+                program
+            ))),
+        },
+        None => Err(Box::new(RudoError::CommandNotFound(program.to_owned()))),
+    }
+}
+
+/// Function to verify that a path is a file with an execute permission
+fn is_executable(path: &PathBuf) -> bool {
+    fs::metadata(path).map_or(false, |metadata| {
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    })
+}
+
+/// Function that start a new command with the pass arguments, the environment of the user
+/// that the rules keep and the environment of the Pam session
+///
+/// # Errors
+/// Return an error if the command couldn't be started, if it couldn't be waited for or if it failed
+pub fn start_command(
+    data: CmdData<'_>,
+    env: Vec<(OsString, OsString)>,
+    env_rules: &EnvRules,
+    user: &users::User,
+) -> Result<(), Box<dyn Error>> {
+    // Start of the command
+    debug!("Start of the command");
+    let CmdData {
+        program,
+        args,
+        digest,
+    } = data;
+    // Keep only the environment of the user the rules allow, then add the environment of the Pam session
+    let mut vars: BTreeMap<OsString, OsString> =
+        env_rules.filter(env::vars_os()).into_iter().collect();
+    vars.extend(env);
+
+    let mut command = Command::new(&program);
+    command
+        .args(&args)
+        .env_clear()
+        .envs(&vars)
+        .uid(user.uid()) // Necessary to have full access
+        .gid(user.primary_group_id()); // Necessary to have full access
+
+    // A pinned program is verified, then executed from a sealed copy of the verified bytes, so it can't be swapped in between
+    let pinned = match digest {
+        Some(ref pinned_digest) => Some(open_pinned(&program, pinned_digest)?),
+        None => None,
+    };
+    if let Some(ref file) = pinned {
+        let exec_args = ExecArgs::new(&program, &args, &vars)?;
+        exec_by_fd(&mut command, file, exec_args);
+    }
+    let mut child = command.spawn().map_err(|err| spawn_error(&err, &program))?;
+
+    // Wait for the command to finish, or the program end before the command
+    let status = child.wait()?;
+    verify_status(status)
+}
+
+/// Function to read the program and verify that its sha256 digest is the pinned one.
+/// The verified bytes are copied in a sealed file, the program could be modified after its verification
+fn open_pinned(program: &str, digest: &str) -> Result<File, Box<dyn Error>> {
+    let bytes = fs::read(program).map_err(|err| spawn_error(&err, program))?;
+    let actual = format!(
+        "{}{}",
+        SHA256_PREFIX,
+        utils::to_hex(&Sha256::digest(&bytes))
+    );
+    if actual.eq_ignore_ascii_case(digest) {
+        debug!("The digest of {} is the pinned one", program);
+        sealed_copy(program, &bytes)
+    } else {
+        journal::audit_event(
+            &format!("The digest of {} isn't the pinned one", program),
+            &[
+                ("RUDO_EVENT", "digest_mismatch"),
+                ("RUDO_COMMAND", program),
+                ("RUDO_DIGEST", &actual),
+            ],
+        );
+        Err(Box::new(RudoError::DigestMismatch(program.to_owned())))
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Copy the verified program in a file in memory, then seal it so it can never be modified
+fn sealed_copy(program: &str, bytes: &[u8]) -> Result<File, Box<dyn Error>> {
+    use std::{io::Write, os::unix::io::FromRawFd};
+
+    let name = CString::new("rudo-pinned")?;
+    let fd =
+        unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(From::from(format!(
+            "Couldn't copy {}: {}",
+            program,
+            io::Error::last_os_error()
+        )));
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(bytes)?;
+    let seals = libc::F_SEAL_WRITE | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(From::from(format!(
+            "Couldn't seal the copy of {}: {}",
+            program,
+            io::Error::last_os_error()
+        )));
+    }
+    Ok(file)
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Refuse a pinned program, there is no sealed file in memory to execute the verified bytes
+fn sealed_copy(program: &str, _bytes: &[u8]) -> Result<File, Box<dyn Error>> {
+    Err(From::from(format!(
+        "{} has a digest, a pinned program can only be executed on Linux",
+        program
+    )))
+}
+
+/// `ExecArgs` are the arguments and the environment given to `fexecve`, with the strings they point to
+struct ExecArgs {
+    /// The arguments, the first is the program
+    _args: Vec<CString>,
+    /// The environment, as `NAME=value`
+    _vars: Vec<CString>,
+    /// The pointers to the arguments, ended by a null pointer
+    argv: Vec<*const c_char>,
+    /// The pointers to the environment, ended by a null pointer
+    envp: Vec<*const c_char>,
+}
+
+// The pointers point to the strings of the structure, that move with it,
+// and they are only read in the child, where there is a single thread
+unsafe impl Send for ExecArgs {}
+unsafe impl Sync for ExecArgs {}
+
+impl ExecArgs {
+    /// Create the arguments and the environment of `fexecve`
+    fn new(
+        program: &str,
+        args: &[&str],
+        vars: &BTreeMap<OsString, OsString>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut arguments = vec![CString::new(program)?];
+        for arg in args {
+            arguments.push(CString::new(*arg)?);
+        }
+        let mut environment = Vec::with_capacity(vars.len());
+        for (name, value) in vars {
+            let mut entry = name.as_bytes().to_vec();
+            entry.push(b'=');
+            entry.extend_from_slice(value.as_bytes());
+            environment.push(CString::new(entry)?);
+        }
+        Ok(Self {
+            argv: null_terminated(&arguments),
+            envp: null_terminated(&environment),
+            _args: arguments,
+            _vars: environment,
+        })
+    }
+}
+
+/// Function to create the array of pointers to the strings, ended by a null pointer, that exec expect
+fn null_terminated(strings: &[CString]) -> Vec<*const c_char> {
+    strings
+        .iter()
+        .map(|string| string.as_ptr())
+        .chain(iter::once(ptr::null()))
+        .collect()
+}
+
+/// Function to replace the execution of the command by the one of the file descriptor of the verified program
+fn exec_by_fd(command: &mut Command, file: &File, exec_args: ExecArgs) {
+    let fd = file.as_raw_fd();
+    unsafe {
+        command.pre_exec(move || fexecve(fd, &exec_args));
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Execute the program of the file descriptor, in the child
+fn fexecve(fd: RawFd, exec_args: &ExecArgs) -> io::Result<()> {
+    // The file must stay open in the program, the interpreter of a script read it by /dev/fd
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { libc::fexecve(fd, exec_args.argv.as_ptr(), exec_args.envp.as_ptr()) };
+    // fexecve only return when it failed
+    Err(io::Error::last_os_error())
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Refuse to execute a pinned program, it's already refused before its copy
+fn fexecve(_fd: RawFd, _exec_args: &ExecArgs) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Other,
+        "a program with a digest can only be executed on Linux, that has fexecve",
+    ))
+}
+
+/// Function to tell apart a command that doesn't exist from one that can't be executed
+fn spawn_error(err: &io::Error, program: &str) -> Box<dyn Error> {
+    match err.kind() {
+        ErrorKind::NotFound => Box::new(RudoError::CommandNotFound(program.to_owned())),
+        ErrorKind::PermissionDenied => {
+            Box::new(RudoError::CommandNotExecutable(program.to_owned()))
+        }
+        _ => From::from(format!("{} couldn't be started: {}", program, err)),
+    }
+}
+
+/// Function to verify that the command succeeded, with its exit code or the signal that killed it
+fn verify_status(status: ExitStatus) -> Result<(), Box<dyn Error>> {
+    if status.success() {
+        debug!("The command succeeded");
+        return Ok(());
+    }
+    let description = match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with the code {}", code),
+        (None, Some(signal)) => format!("was killed by the signal {}", signal),
+        (None, None) => String::from("failed"),
+    };
+    Err(Box::new(RudoError::ChildFailed(description)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        ffi::{OsStr, OsString},
+        fs, io,
+        os::unix::{fs::PermissionsExt, process::ExitStatusExt},
+        path::Path,
+        process::{self, ExitStatus},
+    };
+
+    use super::{resolve_program, spawn_error, start_command, verify_status, CmdData, Error};
+    use crate::{error::RudoError, policy::EnvRules};
+
+    /// The script of the tests, it succeed only with its argument and environment
+    static SCRIPT: &str = "#!/bin/sh\n[ \"$1\" = one ] && [ \"$RUDO_TEST\" = yes ]\n";
+    /// The sha256 digest of the script
+    static SCRIPT_DIGEST: &str =
+        "sha256:46949f0eb9c67667753664c52668fb5358dc0924a93e7ffc7cf3ff2fee708bb7";
+
+    /// Run the script of the tests with its digest, after writing the content in its place
+    fn run_pinned(name: &str, content: &str) -> Result<(), Box<dyn Error>> {
+        let path = env::temp_dir().join(format!("rudo-{}-{}", name, process::id()));
+        fs::write(&path, content)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        let user = match users::get_user_by_uid(users::get_current_uid()) {
+            Some(user) => user,
+            None => return Err(From::from("Couldn't find the user of the test")),
+        };
+        let program = path.to_string_lossy().into_owned();
+        let data = CmdData {
+            program,
+            args: vec!["one"],
+            digest: Some(String::from(SCRIPT_DIGEST)),
+        };
+        let env = vec![(OsString::from("RUDO_TEST"), OsString::from("yes"))];
+        let result = start_command(data, env, &EnvRules::default(), &user);
+        fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn test_command_new() -> Result<(), Box<dyn Error>> {
+        let command = CmdData::new(vec!["test"]);
+        if command.is_ok() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to create structure"))
+        }
+    }
+
+    #[test]
+    fn test_command_new_empty() -> Result<(), Box<dyn Error>> {
+        let command = CmdData::new(vec![]);
+        if command.is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to see an empty vector"))
+        }
+    }
+    #[test]
+    fn test_command_new_full() -> Result<(), Box<dyn Error>> {
+        let command = CmdData::new(vec!["test", "command", "full"])?;
+        if command.program == "test" && command.args == vec!["command", "full"] {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to reproduced structure"))
+        }
+    }
+
+    #[test]
+    fn test_spawn_error() -> Result<(), Box<dyn Error>> {
+        let not_found = spawn_error(&io::Error::from(io::ErrorKind::NotFound), "test");
+        let denied = spawn_error(&io::Error::from(io::ErrorKind::PermissionDenied), "test");
+        if not_found.downcast_ref::<RudoError>()
+            == Some(&RudoError::CommandNotFound(String::from("test")))
+            && denied.downcast_ref::<RudoError>()
+                == Some(&RudoError::CommandNotExecutable(String::from("test")))
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to recognize why the command didn't start",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_status() -> Result<(), Box<dyn Error>> {
+        // The raw status of wait, the exit code is in the second byte and the signal in the first
+        let failed = verify_status(ExitStatus::from_raw(2 << 8));
+        let killed = verify_status(ExitStatus::from_raw(9));
+        if verify_status(ExitStatus::from_raw(0)).is_ok()
+            && failed
+                .err()
+                .and_then(|err| err.downcast_ref::<RudoError>().cloned())
+                == Some(RudoError::ChildFailed(String::from(
+                    "exited with the code 2",
+                )))
+            && killed
+                .err()
+                .and_then(|err| err.downcast_ref::<RudoError>().cloned())
+                == Some(RudoError::ChildFailed(String::from(
+                    "was killed by the signal 9",
+                )))
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to recognize the status of the command",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_resolve_program() -> Result<(), Box<dyn Error>> {
+        let path = OsStr::new("relative:/nonexistent:/bin:/usr/bin");
+        let cwd = Path::new("/tmp");
+        let found = resolve_program("sh", Some(path), cwd)?;
+        let missing = resolve_program("rudo-nonexistent-program", Some(path), cwd);
+        if (found == "/bin/sh" || found == "/usr/bin/sh")
+            && resolve_program("./script", Some(path), cwd)? == "/tmp/./script"
+            && resolve_program("/usr/bin/id", None, cwd)? == "/usr/bin/id"
+            && missing
+                .err()
+                .and_then(|err| err.downcast_ref::<RudoError>().cloned())
+                == Some(RudoError::CommandNotFound(String::from(
+                    "rudo-nonexistent-program",
+                )))
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to resolve the path of the program"))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_start_pinned_command() -> Result<(), Box<dyn Error>> {
+        run_pinned("pinned", SCRIPT)
+    }
+
+    #[test]
+    fn test_start_modified_pinned_command() -> Result<(), Box<dyn Error>> {
+        let modified = format!("{}exit 0\n", SCRIPT);
+        match run_pinned("modified", &modified) {
+            Err(err)
+                if err.downcast_ref::<RudoError>().map_or(false, |rudo_error| {
+                    matches!(rudo_error, RudoError::DigestMismatch(_))
+                }) =>
+            {
+                Ok(())
+            }
+            _ => Err(From::from("Test failed: a modified program was executed")),
+        }
+    }
+}
@@ -0,0 +1,815 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{error::Error, fmt, fs, path::Path, slice, time::Duration};
+
+use log::{debug, error, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alias::{self, Aliases},
+    error::RudoError,
+    host,
+    schedule::{AllowedTimes, Timestamp},
+    utils, CONFIG_PATH,
+};
+
+/// The prefix of the sha256 digest of a command rule
+pub static SHA256_PREFIX: &str = "sha256:";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+/// `UserConf` structure is the representation of the data of a part of the configuration file
+pub struct UserConf {
+    /// The Unix username of an authorized user, or the name of a users alias
+    pub username: String,
+    /// The group the user must be a member to have authorization to use Rudo
+    pub group: String,
+    /// A Boolean to determine if the user must give is password or not
+    pub password: bool,
+    /// A Boolean to determine if the user want to be saluted every time Rudo is invoked
+    pub greeting: bool,
+    /// Replace the number of time the password is asked of `RudoConf` for this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passwd_tries: Option<u8>,
+    /// Replace the delay in seconds between two password attempts of `RudoConf` for this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passwd_delay: Option<u64>,
+    /// Replace the message of a wrong password of `RudoConf` for this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub badpass_message: Option<String>,
+    /// Replace the password prompt of `RudoConf` for this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passprompt: Option<String>,
+    /// The account whose password is asked to the user
+    #[serde(default)]
+    pub auth_as: AuthAs,
+    /// A Boolean to determine if the environment of the user is removed from the command
+    #[serde(default)]
+    pub env_reset: bool,
+    /// The variables of the environment of the user that are kept when it's removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_keep: Vec<String>,
+    /// The commands the user may run, every command is allowed if there is none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<CommandRule>,
+    /// The users, or runas aliases, the user may impersonate, every user is allowed if there is none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runas: Vec<String>,
+    /// The hosts, or hosts aliases, where the rule apply, every host is allowed if there is none.
+    /// A host is a glob of the hostname or the FQDN, or an IP address or network of a local interface
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// The windows of the week when the rule allow commands, at any time if there is none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_times: Option<AllowedTimes>,
+    /// The rule is granted from this instant, like `2026-10-19T18:00:00Z`, it's ignored before
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Timestamp>,
+    /// The rule is granted until this instant, it's ignored after as if it was absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<Timestamp>,
+}
+
+/// `Validity` is if the grant of a rule is in effect at an instant
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Validity {
+    /// The rule is in effect
+    Active,
+    /// The rule isn't in effect yet, it's before its `not_before`
+    Pending,
+    /// The rule isn't in effect anymore, it's after its `not_after`
+    Expired,
+}
+
+/// `CommandRule` is a command the user may run, and the arguments it may have
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CommandRule {
+    /// The absolute path of the program, or the name of a commands alias
+    pub path: String,
+    /// The patterns of the arguments, one by argument. A pattern is the exact argument,
+    /// a glob where `*` match any characters, or an anchored regex between `^` and `$`.
+    /// An empty list allow no argument, and any argument is allowed without the list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+    /// The digest of the program, as `sha256:` and 64 hexadecimal digits, to refuse a modified program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl CommandRule {
+    /// Function to verify that every regex of the patterns of the arguments is valid, and the format of the digest
+    ///
+    /// # Errors
+    /// Return an error with the invalid pattern if a regex couldn't be compiled, or if the digest isn't a sha256 one
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(ref digest) = self.digest {
+            if !is_sha256_digest(digest) {
+                return Err(From::from(format!(
+                    "invalid digest {} of {}, it must be sha256: and 64 hexadecimal digits",
+                    digest, self.path
+                )));
+            }
+        }
+        for pattern in self.args.iter().flatten() {
+            if let Some(Err(err)) = arg_regex(pattern) {
+                return Err(From::from(format!(
+                    "invalid regex {} in the arguments of {}: {}",
+                    pattern, self.path, err
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Function to verify the format of a digest, `sha256:` and 64 hexadecimal digits
+fn is_sha256_digest(digest: &str) -> bool {
+    let hex = if digest.starts_with(SHA256_PREFIX) {
+        digest.get(SHA256_PREFIX.len()..).unwrap_or_default()
+    } else {
+        return false;
+    };
+    hex.len() == 64 && hex.chars().all(|character| character.is_ascii_hexdigit())
+}
+
+/// Function to compile the pattern of an argument if it's an anchored regex, between `^` and `$`.
+/// The regex is anchored as a whole, so an alternation can't escape it
+#[must_use]
+pub fn arg_regex(pattern: &str) -> Option<Result<Regex, regex::Error>> {
+    if pattern.len() < 2 || !pattern.starts_with('^') || !pattern.ends_with('$') {
+        return None;
+    }
+    let inner = pattern.get(1..pattern.len().saturating_sub(1))?;
+    Some(Regex::new(&format!("^(?:{})$", inner)))
+}
+
+/// `AuthAs` is the account that Pam authenticate, as written in the configuration file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthAs {
+    /// The user that invoke Rudo give its own password
+    #[serde(rename = "self")]
+    Invoker,
+    /// The password of the impersonated user is asked
+    Target,
+    /// The password of root is asked
+    Root,
+}
+
+impl AuthAs {
+    /// Give the name of the account that Pam must authenticate
+    #[must_use]
+    pub fn account<'a>(self, user: &'a str, target: &'a str) -> &'a str {
+        match self {
+            Self::Invoker => user,
+            Self::Target => target,
+            Self::Root => "root",
+        }
+    }
+}
+
+impl Default for AuthAs {
+    fn default() -> Self {
+        Self::Invoker
+    }
+}
+
+impl fmt::Display for AuthAs {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Invoker => write!(formatter, "self"),
+            Self::Target => write!(formatter, "target"),
+            Self::Root => write!(formatter, "root"),
+        }
+    }
+}
+
+impl UserConf {
+    /// Function to know if the grant of the rule is in effect at an instant, in seconds since the epoch
+    #[must_use]
+    pub fn validity(&self, now: i64) -> Validity {
+        if self
+            .not_before
+            .as_ref()
+            .map_or(false, |not_before| now < not_before.seconds())
+        {
+            Validity::Pending
+        } else if self
+            .not_after
+            .as_ref()
+            .map_or(false, |not_after| now > not_after.seconds())
+        {
+            Validity::Expired
+        } else {
+            Validity::Active
+        }
+    }
+    /// Function to update the greeting Boolean if the "-g" option was given
+    #[must_use]
+    pub fn update_greeting(mut self) -> Self {
+        // Update greeting value with CLI option
+        debug!("Greeting value will be update");
+        self.greeting = true;
+        self
+    }
+}
+
+impl Default for UserConf {
+    fn default() -> Self {
+        Self {
+            username: String::from("root"),
+            group: String::from("wheel"),
+            password: true,
+            greeting: true,
+            passwd_tries: None,
+            passwd_delay: None,
+            badpass_message: None,
+            passprompt: None,
+            auth_as: AuthAs::default(),
+            env_reset: false,
+            env_keep: Vec::new(),
+            commands: Vec::new(),
+            runas: Vec::new(),
+            hosts: Vec::new(),
+            allowed_times: None,
+            not_before: None,
+            not_after: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+/// `RudoConf` is where the program stock is configuration
+pub struct RudoConf {
+    /// impuser is the Unix name of the user you want to impersonate
+    pub impuser: String,
+    /// The number of time the password is asked before Rudo give up
+    #[serde(default = "default_passwd_tries")]
+    pub passwd_tries: u8,
+    /// The delay in seconds between two password attempts
+    #[serde(default = "default_passwd_delay")]
+    pub passwd_delay: u64,
+    /// The message show to the user when the password is incorrect
+    #[serde(default = "default_badpass_message")]
+    pub badpass_message: String,
+    /// The prompt that replace the one of Pam when the password is asked, with placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passprompt: Option<String>,
+    /// The number of failed authentication before the user is locked out of Rudo, 0 to disable it
+    #[serde(default = "default_lockout_attempts")]
+    pub lockout_attempts: u8,
+    /// The time in seconds the user stay locked out of Rudo
+    #[serde(default = "default_lockout_time")]
+    pub lockout_time: u64,
+    /// The name of the Pam service, its file is in /etc/pam.d/
+    #[serde(default = "default_pam_service")]
+    pub pam_service: String,
+    /// The name of the Pam service of a login shell, `pam_service` is used if there is none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pam_login_service: Option<String>,
+}
+
+impl RudoConf {
+    /// Function to choose the Pam service, as a login shell can have its own
+    #[must_use]
+    pub fn pam_service(&self, login: bool) -> &str {
+        match self.pam_login_service {
+            Some(ref service) if login => service,
+            _ => &self.pam_service,
+        }
+    }
+    /// Function to merge the password options with the one of the user, as the user as the priority
+    #[must_use]
+    pub fn password_conf(&self, userconf: &UserConf) -> PasswordConf {
+        let tries = userconf.passwd_tries.unwrap_or(self.passwd_tries);
+        let delay = userconf.passwd_delay.unwrap_or(self.passwd_delay);
+        let badpass_message = match userconf.badpass_message {
+            Some(ref message) => message.clone(),
+            None => self.badpass_message.clone(),
+        };
+        let prompt = userconf
+            .passprompt
+            .as_ref()
+            .or(self.passprompt.as_ref())
+            .cloned();
+        PasswordConf {
+            required: userconf.password,
+            // The password must be asked at least one time
+            tries: tries.max(1),
+            delay: Duration::from_secs(delay),
+            badpass_message,
+            prompt,
+            auth_as: userconf.auth_as,
+        }
+    }
+}
+
+impl Default for RudoConf {
+    fn default() -> Self {
+        Self {
+            impuser: String::from("root"),
+            passwd_tries: default_passwd_tries(),
+            passwd_delay: default_passwd_delay(),
+            badpass_message: default_badpass_message(),
+            passprompt: None,
+            lockout_attempts: default_lockout_attempts(),
+            lockout_time: default_lockout_time(),
+            pam_service: default_pam_service(),
+            pam_login_service: None,
+        }
+    }
+}
+
+/// The default number of time the password is asked
+fn default_passwd_tries() -> u8 {
+    3
+}
+
+/// The default delay in seconds between two password attempts
+fn default_passwd_delay() -> u64 {
+    2
+}
+
+/// The default message when the password is incorrect
+fn default_badpass_message() -> String {
+    String::from("Password was incorrect! Will be report to administrator!")
+}
+
+/// The default number of failed authentication before the user is locked out
+fn default_lockout_attempts() -> u8 {
+    5
+}
+
+/// The default time in seconds the user stay locked out
+fn default_lockout_time() -> u64 {
+    900
+}
+
+/// The default name of the Pam service
+fn default_pam_service() -> String {
+    String::from("rudo")
+}
+
+/// `PasswordConf` is how the password must be asked to the user
+#[derive(Debug)]
+pub struct PasswordConf {
+    /// A Boolean to determine if the user must give is password or not
+    pub required: bool,
+    /// The number of time the password is asked
+    pub tries: u8,
+    /// The delay between two attempts
+    pub delay: Duration,
+    /// The message show when the password is incorrect
+    pub badpass_message: String,
+    /// The prompt that replace the one of Pam, before its placeholders are replaced
+    pub prompt: Option<String>,
+    /// The account whose password is asked
+    pub auth_as: AuthAs,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Config is the sum of `UserConf` and `RudoConf` as represent in the configuration file
+pub struct Config {
+    /// rudo is where the program stock is configuration
+    pub rudo: RudoConf,
+    /// user is where a vector of user configuration is stock to permit multiple user configuration
+    pub user: Vec<UserConf>,
+    /// aliases is where the named sets of commands, users and hosts the rules reference are stock
+    #[serde(default, skip_serializing_if = "Aliases::is_empty")]
+    pub aliases: Aliases,
+}
+
+impl Config {
+    /// Function to create the configuration file with the right permissions, and it's data
+    fn create_config_file(&self) -> Result<(), Box<dyn Error>> {
+        // Create the path for the configuration
+        let config_path = Path::new(CONFIG_PATH);
+        // Transform the structure to YAML
+        debug!("Creating default data for configuration file");
+        let config_file = serde_yaml::to_string(&self)?;
+        // Create the configuration file
+        debug!("Creating configuration file at {}", CONFIG_PATH);
+        utils::create_file(config_path, 0o640, &config_file)?;
+        Ok(())
+    }
+    /// Function to verify what serde can't, like the regex of the command rules and the references to the aliases
+    ///
+    /// # Errors
+    /// Return a `ConfigParse` error that explain which alias or rule is invalid
+    pub fn validate(&self) -> Result<(), RudoError> {
+        self.aliases
+            .validate()
+            .map_err(|err| RudoError::ConfigParse(format!("aliases: {}", err)))?;
+        for userconf in &self.user {
+            self.validate_userconf(userconf).map_err(|err| {
+                RudoError::ConfigParse(format!("user {}: {}", userconf.username, err))
+            })?;
+        }
+        Ok(())
+    }
+    /// Function to verify that the aliases of a rule are defined, its host networks, its command rules,
+    /// its windows and its period of validity
+    fn validate_userconf(&self, userconf: &UserConf) -> Result<(), Box<dyn Error>> {
+        self.aliases
+            .expand_users(slice::from_ref(&userconf.username))?;
+        self.aliases.expand_runas(&userconf.runas)?;
+        for pattern in self.aliases.expand_hosts(&userconf.hosts)? {
+            host::validate_pattern(&pattern)?;
+        }
+        alias::verify_command_references(&userconf.commands)?;
+        for rule in self.aliases.expand_commands(&userconf.commands)? {
+            rule.validate()?;
+        }
+        if let (Some(not_before), Some(not_after)) = (&userconf.not_before, &userconf.not_after) {
+            if not_before.seconds() > not_after.seconds() {
+                return Err(From::from(format!(
+                    "not_before {} is after not_after {}",
+                    not_before, not_after
+                )));
+            }
+        }
+        if let Some(ref allowed_times) = userconf.allowed_times {
+            if allowed_times.windows.is_empty() {
+                return Err(From::from(
+                    "allowed_times has no window, no command would ever be allowed",
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// Function to update the name of the impersonated user with the value give in the command-line
+    #[must_use]
+    pub fn update_user(mut self, impuser: String) -> Self {
+        // Update user value with CLI value
+        debug!("User value will be update");
+        self.rudo.impuser = impuser;
+        self
+    }
+}
+// Default value for configuration
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rudo: RudoConf::default(),
+            user: vec![UserConf::default()],
+            aliases: Aliases::default(),
+        }
+    }
+}
+/// Function to initialize the configuration with the default data if necessary
+///
+/// # Errors
+/// Return an error if the configuration file couldn't be created or read
+pub fn init_conf() -> Result<Config, Box<dyn Error>> {
+    // Initialize configuration with defaults
+    debug!("Begin initializing default configuration for further use");
+    let mut conf = Config::default();
+
+    // Verify that the file is there or write to it with the defaults
+    let path = Path::new(CONFIG_PATH);
+    debug!("Verifying that {} exist", CONFIG_PATH);
+    if path.exists() && path.is_file() {
+        // Load the file and verify its validity
+        debug!("Loading {}", CONFIG_PATH);
+        let result = read_config_file(path);
+        if let Err(err) = result {
+            error!("{}", err);
+            return Err(err);
+        }
+        // Return the valid data of the configuration file
+        debug!("Returning the content of the configuration file");
+        conf = result?;
+    } else if path.exists() && path.is_dir() {
+        // Error if it's a directory and let the user decide what to do
+        let err = format!("Error: {} is a directory", CONFIG_PATH);
+        error!("{}", err);
+        return Err(From::from(err));
+    } else {
+        // Create a configuration file if it doesn't exist
+        warn!("{} doesn't exist! Creating it", CONFIG_PATH);
+        eprintln!("{} doesn't exist! Creating it", CONFIG_PATH);
+        conf.create_config_file()?;
+    }
+    Ok(conf)
+}
+
+/// Function to read the configuration file at `path` and extract its data, it's never created
+///
+/// # Errors
+/// Return an error if the configuration file couldn't be read or parsed
+pub fn read_config_file(path: &Path) -> Result<Config, Box<dyn Error>> {
+    // Open the existing configuration file
+    debug!("Opening configuration file at {}", path.display());
+    let buffer = fs::read_to_string(path)
+        .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+    parse_config(&buffer)
+}
+
+/// Function to transform the content of a configuration file to a valid `Config`
+///
+/// # Errors
+/// Return a `RudoError::ConfigParse` if serde refuses the data or if the configuration isn't valid
+pub fn parse_config(buffer: &str) -> Result<Config, Box<dyn Error>> {
+    // transform data to structure with serde
+    debug!("Transform data to a structure with serde");
+    let config: Config =
+        serde_yaml::from_str(buffer).map_err(|err| RudoError::ConfigParse(err.to_string()))?;
+    config.validate()?;
+    // Return the configuration
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        parse_config, read_config_file, AuthAs, CommandRule, Config, Duration, Error, RudoConf,
+        UserConf, Validity,
+    };
+    use crate::error::exit_code;
+
+    #[test]
+    fn test_update_greeting() -> Result<(), Box<dyn Error>> {
+        let conf = UserConf {
+            username: String::from("nano"),
+            group: String::from("micro"),
+            password: false,
+            greeting: false,
+            ..UserConf::default()
+        };
+        let conf = conf.update_greeting();
+        if conf.greeting {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to update greeting value!"))
+        }
+    }
+
+    #[test]
+    fn test_update_user() -> Result<(), Box<dyn Error>> {
+        let conf = Config::default();
+        let conf = conf.update_user(String::from("nano"));
+        if conf.rudo.impuser == "nano" {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to update impuser value!"))
+        }
+    }
+
+    #[test]
+    fn test_password_conf() -> Result<(), Box<dyn Error>> {
+        let rudoconf = RudoConf {
+            passprompt: Some(String::from("Password of %p: ")),
+            ..RudoConf::default()
+        };
+        let userconf = UserConf {
+            passwd_tries: Some(0),
+            badpass_message: Some(String::from("Wrong")),
+            ..UserConf::default()
+        };
+        let password_conf = rudoconf.password_conf(&userconf);
+        if password_conf.tries == 1
+            && password_conf.delay == Duration::from_secs(2)
+            && password_conf.badpass_message == "Wrong"
+            && password_conf.prompt == Some(String::from("Password of %p: "))
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to merge the password options"))
+        }
+    }
+
+    #[test]
+    fn test_read_old_config() -> Result<(), Box<dyn Error>> {
+        let data = "rudo:\n  impuser: root\nuser:\n  - username: root\n    group: wheel\n    password: true\n    greeting: true\n";
+        let conf: Config = serde_yaml::from_str(data)?;
+        let userconf = conf.user.first().cloned().unwrap_or_default();
+        if conf.rudo.passwd_tries == 3
+            && userconf.passwd_tries.is_none()
+            && userconf.auth_as == AuthAs::Invoker
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to read a configuration without the new options",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_parse_config_exit_code() -> Result<(), Box<dyn Error>> {
+        let unparsable = parse_config("rudo:\n  impuser: [root\n");
+        let invalid = parse_config(
+            "rudo:\n  impuser: root\nuser:\n  - username: root\n    group: wheel\n    password: true\n    greeting: true\n    commands:\n      - path: /bin/ls\n        digest: sha256:ls\n",
+        );
+        let valid = parse_config(
+            "rudo:\n  impuser: root\nuser:\n  - username: root\n    group: wheel\n    password: true\n    greeting: true\n",
+        );
+        match (unparsable, invalid, valid) {
+            (Err(parse_error), Err(validation_error), Ok(_))
+                if exit_code(parse_error.as_ref()) == 2
+                    && exit_code(validation_error.as_ref()) == 2 =>
+            {
+                Ok(())
+            }
+            _ => Err(From::from(
+                "Test failed to exit with the configuration error code",
+            )),
+        }
+    }
+
+    #[test]
+    fn test_read_missing_config_file() -> Result<(), Box<dyn Error>> {
+        let path = Path::new("test_missing_rudo.conf");
+        if read_config_file(path).is_err() && !path.exists() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: a missing configuration file was read or created",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_auth_as() -> Result<(), Box<dyn Error>> {
+        let data =
+            "username: alice\ngroup: wheel\npassword: true\ngreeting: false\nauth_as: target\n";
+        let userconf: UserConf = serde_yaml::from_str(data)?;
+        if userconf.auth_as == AuthAs::Target
+            && userconf.auth_as.account("alice", "bob") == "bob"
+            && AuthAs::Root.account("alice", "bob") == "root"
+            && AuthAs::Invoker.account("alice", "bob") == "alice"
+            && serde_yaml::from_str::<AuthAs>("self")? == AuthAs::Invoker
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to read the account to authenticate",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_pam_service() -> Result<(), Box<dyn Error>> {
+        let mut rudoconf = RudoConf::default();
+        let without_login = rudoconf.pam_service(true) == "rudo";
+        rudoconf.pam_login_service = Some(String::from("rudo-i"));
+        if without_login
+            && rudoconf.pam_service(true) == "rudo-i"
+            && rudoconf.pam_service(false) == "rudo"
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to choose the Pam service"))
+        }
+    }
+
+    #[test]
+    fn test_command_rules() -> Result<(), Box<dyn Error>> {
+        let data = "username: alice\ngroup: wheel\npassword: true\ngreeting: false\ncommands:\n  - path: /usr/bin/systemctl\n    args: [restart, \"app-*\"]\n  - path: /usr/bin/uptime\n    args: []\n  - path: /usr/bin/id\n";
+        let userconf: UserConf = serde_yaml::from_str(data)?;
+        let mut conf = Config::default();
+        conf.user.push(userconf.clone());
+        let valid = conf.validate().is_ok();
+        if let Some(alice) = conf.user.get_mut(1) {
+            alice.commands.push(CommandRule {
+                path: String::from("/usr/bin/journalctl"),
+                args: Some(vec![String::from("^(unclosed$")]),
+                digest: None,
+            });
+        }
+        if valid
+            && conf.validate().is_err()
+            && userconf.commands.get(1).and_then(|rule| rule.args.clone()) == Some(Vec::new())
+            && userconf
+                .commands
+                .get(2)
+                .map_or(false, |rule| rule.args.is_none())
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to read and validate the command rules",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_command_digest() -> Result<(), Box<dyn Error>> {
+        let mut rule = CommandRule {
+            path: String::from("/usr/local/bin/helper"),
+            args: None,
+            digest: Some(format!("sha256:{}", "aB".repeat(32))),
+        };
+        let valid = rule.validate().is_ok();
+        rule.digest = Some(format!("md5:{}", "ab".repeat(16)));
+        let wrong_algorithm = rule.validate().is_err();
+        rule.digest = Some(format!("sha256:{}", "xy".repeat(32)));
+        if valid && wrong_algorithm && rule.validate().is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to validate the digest of the command",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_aliases() -> Result<(), Box<dyn Error>> {
+        let data = "rudo:\n  impuser: root\nuser:\n  - username: ADMINS\n    group: wheel\n    password: true\n    greeting: false\n    runas: [SERVICES]\n    commands:\n      - path: WEB\naliases:\n  commands:\n    WEB:\n      - path: /usr/bin/systemctl\n        args: [restart, nginx]\n  users:\n    ADMINS: [alice, bob]\n  runas:\n    SERVICES: [www-data]\n";
+        let mut conf: Config = serde_yaml::from_str(data)?;
+        let valid = conf.validate().is_ok();
+        let bob_matches = conf.aliases.user_matches("ADMINS", "bob");
+        if let Some(admins) = conf.user.get_mut(0) {
+            admins.hosts.push(String::from("WEB_HOSTS"));
+        }
+        let undefined = conf.validate().map_err(|err| err.to_string()).err();
+        if let Some(admins) = conf.user.get_mut(0) {
+            admins.hosts.clear();
+            if let Some(reference) = admins.commands.get_mut(0) {
+                reference.args = Some(vec![String::from("stop")]);
+            }
+        }
+        let reference_with_args = conf.validate().is_err();
+        if valid
+            && bob_matches
+            && reference_with_args
+            && undefined
+                == Some(String::from(
+                    "The configuration file is invalid: user ADMINS: the hosts alias WEB_HOSTS isn't defined",
+                ))
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to validate the aliases of the rules"))
+        }
+    }
+
+    #[test]
+    fn test_allowed_times() -> Result<(), Box<dyn Error>> {
+        let data = "username: alice\ngroup: wheel\npassword: true\ngreeting: false\nallowed_times:\n  timezone: UTC\n  windows:\n    - days: mon-fri\n      hours: 09:00-17:00\n";
+        let userconf: UserConf = serde_yaml::from_str(data)?;
+        let mut conf = Config::default();
+        conf.user.push(userconf);
+        let valid = conf.validate().is_ok();
+        if let Some(times) = conf
+            .user
+            .get_mut(1)
+            .and_then(|alice| alice.allowed_times.as_mut())
+        {
+            times.windows.clear();
+        }
+        let invalid_hours = serde_yaml::from_str::<UserConf>(&data.replace("17:00", "25:00"));
+        let invalid_timezone =
+            serde_yaml::from_str::<UserConf>(&data.replace("UTC", "Nowhere/Atlantis"));
+        if valid && conf.validate().is_err() && invalid_hours.is_err() && invalid_timezone.is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to read and validate the allowed times",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_validity() -> Result<(), Box<dyn Error>> {
+        let data = "username: alice\ngroup: wheel\npassword: true\ngreeting: false\nnot_before: 2026-10-19T18:00:00Z\nnot_after: 2026-10-20T18:00:00Z\n";
+        let userconf: UserConf = serde_yaml::from_str(data)?;
+        let start = 1_792_432_800;
+        let mut conf = Config::default();
+        conf.user.push(userconf.clone());
+        let valid = conf.validate().is_ok();
+        conf.user.push(serde_yaml::from_str(
+            &data.replace("2026-10-20T18:00:00Z", "2026-10-18T18:00:00Z"),
+        )?);
+        if valid
+            && conf.validate().is_err()
+            && userconf.validity(start - 1) == Validity::Pending
+            && userconf.validity(start) == Validity::Active
+            && userconf.validity(start + 86_400) == Validity::Active
+            && userconf.validity(start + 86_401) == Validity::Expired
+            && UserConf::default().validity(start) == Validity::Active
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to know the validity of the rule"))
+        }
+    }
+}
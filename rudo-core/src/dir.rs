@@ -0,0 +1,498 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    ffi::CString,
+    fmt,
+    fs::{File, Permissions},
+    io::{self, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, PermissionsExt},
+        io::{AsRawFd, FromRawFd, RawFd},
+    },
+    path::Path,
+    process,
+};
+
+use libc::{
+    flock, mkdirat, open, openat, renameat, unlinkat, EEXIST, ELOOP, ENOENT, LOCK_EX, O_CLOEXEC,
+    O_CREAT, O_DIRECTORY, O_EXCL, O_NOFOLLOW, O_RDONLY, O_WRONLY, S_IFDIR, S_IFMT, S_IFREG,
+};
+use log::{debug, info};
+
+use crate::journal;
+
+/// The content of a file, or `None` if the file doesn't exist
+pub(crate) type FileData = Option<Vec<u8>>;
+
+/// `UntrustedFile` is the error of a file that Rudo didn't put there, like a symlink or a file with another owner
+#[derive(Debug)]
+pub(crate) struct UntrustedFile {
+    /// The description of the problem, already reported as a security event
+    message: String,
+}
+
+impl fmt::Display for UntrustedFile {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.message)
+    }
+}
+
+impl Error for UntrustedFile {}
+
+/// `Dir` is an opened directory, every operation is done relative to it with the `*at` functions,
+/// so no symlink or swapped path can redirect Rudo outside it
+#[derive(Debug)]
+pub struct Dir {
+    /// The file that own the file descriptor of the directory
+    file: File,
+    /// The path of the directory, only for the messages
+    path: String,
+    /// The UID that must own the directory and everything inside it
+    owner: u32,
+}
+
+impl Dir {
+    /// Open an existing directory that must be owned by `owner`, without following a symlink at its end
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be opened, or if it isn't a directory owned by `owner`
+    pub fn open(path: &Path, owner: u32) -> Result<Self, Box<dyn Error>> {
+        let path_c = CString::new(path.as_os_str().as_bytes())?;
+        let fd = unsafe {
+            open(
+                path_c.as_ptr(),
+                O_RDONLY | O_DIRECTORY | O_NOFOLLOW | O_CLOEXEC,
+            )
+        };
+        let dir = Self::from_fd(fd, path.display().to_string(), owner)?;
+        dir.verify(S_IFDIR, None)?;
+        Ok(dir)
+    }
+    /// Open the directory at `path`, or create it with `mode` if it doesn't exist.
+    /// Its parent must already exist and only the last directory is managed by Rudo
+    ///
+    /// # Errors
+    /// Return an error if a directory couldn't be opened or created, or if it has the wrong owner or mode
+    pub fn open_or_create_path(path: &Path, owner: u32, mode: u32) -> Result<Self, Box<dyn Error>> {
+        // Split the path in the directory that must already exist and the one Rudo manage
+        let (parent, name) = match (
+            path.parent(),
+            path.file_name().and_then(|name| name.to_str()),
+        ) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => {
+                return Err(From::from(format!(
+                    "Couldn't split the path {}",
+                    path.display()
+                )))
+            }
+        };
+        debug!("Opening {}", parent.display());
+        let parent_dir = Self::open(parent, owner)?;
+        debug!(
+            "Opening or creating {} with mode {:o}",
+            path.display(),
+            mode
+        );
+        parent_dir.open_or_create_dir(name, mode)
+    }
+    /// Open the subdirectory `name` or create it with `mode` if it doesn't exist.
+    /// A symlink, a directory with another owner or with other permissions is refused, never fixed
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be opened or created, or if it has the wrong owner or mode
+    pub fn open_or_create_dir(&self, name: &str, mode: u32) -> Result<Self, Box<dyn Error>> {
+        let name_c = file_name(name)?;
+        let created = unsafe { mkdirat(self.file.as_raw_fd(), name_c.as_ptr(), mode) } == 0;
+        if created {
+            info!(
+                "{}/{} doesn't exist, created it with mode {:o}",
+                self.path, name, mode
+            );
+        } else if io::Error::last_os_error().raw_os_error() != Some(EEXIST) {
+            return Err(From::from(io::Error::last_os_error()));
+        } else {
+            debug!("{}/{} already exist", self.path, name);
+        }
+        let dir = self.open_subdir(name)?;
+        if created {
+            // The umask of the user could have removed some permissions
+            dir.file.set_permissions(Permissions::from_mode(mode))?;
+        }
+        dir.verify(S_IFDIR, Some(mode))?;
+        Ok(dir)
+    }
+    /// Open the existing subdirectory `name`.
+    /// A symlink, a directory with another owner or with other permissions than `mode` is refused
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be opened, or if it has the wrong owner or mode
+    pub fn open_dir(&self, name: &str, mode: u32) -> Result<Self, Box<dyn Error>> {
+        let dir = self.open_subdir(name)?;
+        dir.verify(S_IFDIR, Some(mode))?;
+        Ok(dir)
+    }
+    #[cfg(target_os = "linux")]
+    /// List the name of every entry of the directory
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be read
+    pub fn entries(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        use std::fs;
+
+        // The directory is listed through its file descriptor, not its path
+        let mut names = Vec::new();
+        for entry in fs::read_dir(format!("/proc/self/fd/{}", self.file.as_raw_fd()))? {
+            if let Some(name) = entry?.file_name().to_str() {
+                names.push(name.to_owned());
+            }
+        }
+        Ok(names)
+    }
+    #[cfg(target_os = "macos")]
+    /// List the name of every entry of the directory, with `fdopendir` as there is no /proc
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be read
+    pub fn entries(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        use std::ffi::CStr;
+
+        // fdopendir take the file descriptor and closedir close it, so it's given a duplicate
+        let fd = unsafe { libc::dup(self.file.as_raw_fd()) };
+        if fd < 0 {
+            return Err(From::from(io::Error::last_os_error()));
+        }
+        let stream = unsafe { libc::fdopendir(fd) };
+        if stream.is_null() {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(From::from(err));
+        }
+        // The duplicate share the position of the directory, a previous listing could have left it at the end
+        unsafe { libc::rewinddir(stream) };
+        let mut names = Vec::new();
+        loop {
+            let entry = unsafe { libc::readdir(stream) };
+            if entry.is_null() {
+                break;
+            }
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+            match name.to_str() {
+                Ok(".") | Ok("..") | Err(_) => {}
+                Ok(name) => names.push(name.to_owned()),
+            }
+        }
+        unsafe { libc::closedir(stream) };
+        Ok(names)
+    }
+    /// Read the file `name` if it's a regular file, not a symlink, owned by the owner of the directory and with `mode`.
+    /// Return `None` if the file doesn't exist
+    ///
+    /// # Errors
+    /// Return an error if the file couldn't be read, or if it has the wrong type, owner or mode
+    pub fn read_file(&self, name: &str, mode: u32) -> Result<FileData, Box<dyn Error>> {
+        let name_c = file_name(name)?;
+        let fd = unsafe {
+            openat(
+                self.file.as_raw_fd(),
+                name_c.as_ptr(),
+                O_RDONLY | O_NOFOLLOW | O_CLOEXEC,
+            )
+        };
+        if fd < 0 && io::Error::last_os_error().raw_os_error() == Some(ENOENT) {
+            return Ok(None);
+        }
+        let mut dir = Self::from_fd(fd, format!("{}/{}", self.path, name), self.owner)?;
+        dir.verify(S_IFREG, Some(mode))?;
+        let mut buffer = Vec::new();
+        io::copy(&mut dir.file, &mut buffer)?;
+        Ok(Some(buffer))
+    }
+    /// Write the file `name` with `mode`. The data is written to a temporary file
+    /// then renamed, so the file is always complete and a symlink in its place is replaced, not followed
+    ///
+    /// # Errors
+    /// Return an error if the name is invalid or if the file couldn't be written
+    pub fn write_file(&self, name: &str, mode: u32, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let name_c = file_name(name)?;
+        let tmp_c = file_name(&format!(".{}.{}", name, process::id()))?;
+
+        // Remove a temporary file that a previous crash could have left behind
+        if unsafe { unlinkat(self.file.as_raw_fd(), tmp_c.as_ptr(), 0) } != 0
+            && io::Error::last_os_error().raw_os_error() != Some(ENOENT)
+        {
+            return Err(From::from(io::Error::last_os_error()));
+        }
+
+        debug!("Writing {}/{} in a temporary file", self.path, name);
+        let fd = unsafe {
+            openat(
+                self.file.as_raw_fd(),
+                tmp_c.as_ptr(),
+                O_WRONLY | O_CREAT | O_EXCL | O_NOFOLLOW | O_CLOEXEC,
+                mode,
+            )
+        };
+        let mut tmp = Self::from_fd(fd, format!("{}/{}", self.path, name), self.owner)?;
+        tmp.file.set_permissions(Permissions::from_mode(mode))?;
+        tmp.file.write_all(data)?;
+        tmp.file.sync_all()?;
+
+        debug!("Renaming the temporary file to {}/{}", self.path, name);
+        let fd = self.file.as_raw_fd();
+        if unsafe { renameat(fd, tmp_c.as_ptr(), fd, name_c.as_ptr()) } != 0 {
+            let err = io::Error::last_os_error();
+            self.remove_file(&format!(".{}.{}", name, process::id()))?;
+            return Err(From::from(err));
+        }
+        Ok(())
+    }
+    /// Remove the file `name` from the directory, it's not an error if it doesn't exist
+    ///
+    /// # Errors
+    /// Return an error if the name is invalid or if the file couldn't be removed
+    pub fn remove_file(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let name_c = file_name(name)?;
+        if unsafe { unlinkat(self.file.as_raw_fd(), name_c.as_ptr(), 0) } != 0
+            && io::Error::last_os_error().raw_os_error() != Some(ENOENT)
+        {
+            return Err(From::from(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+    /// Lock the directory for this process, the others wait until the directory is closed.
+    /// The directory is locked instead of its files, as they are replaced when they are written
+    ///
+    /// # Errors
+    /// Return an error if the directory couldn't be locked
+    pub fn lock(&self) -> Result<(), Box<dyn Error>> {
+        debug!("Locking {}", self.path);
+        if unsafe { flock(self.file.as_raw_fd(), LOCK_EX) } != 0 {
+            return Err(From::from(format!(
+                "Couldn't lock {}: {}",
+                self.path,
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+    /// Open the subdirectory `name` without following a symlink, the caller must verify it
+    fn open_subdir(&self, name: &str) -> Result<Self, Box<dyn Error>> {
+        let name_c = file_name(name)?;
+        let fd = unsafe {
+            openat(
+                self.file.as_raw_fd(),
+                name_c.as_ptr(),
+                O_RDONLY | O_DIRECTORY | O_NOFOLLOW | O_CLOEXEC,
+            )
+        };
+        Self::from_fd(fd, format!("{}/{}", self.path, name), self.owner)
+    }
+    /// Take the ownership of a file descriptor return by `open` or `openat`.
+    /// A failure other than a missing file is reported as a security event, like a symlink in place of the file
+    fn from_fd(fd: RawFd, path: String, owner: u32) -> Result<Self, Box<dyn Error>> {
+        if fd < 0 {
+            let os_err = io::Error::last_os_error();
+            let err = match os_err.raw_os_error() {
+                Some(ENOENT) => {
+                    return Err(From::from(format!("Couldn't open {}: {}", path, os_err)))
+                }
+                Some(ELOOP) => return Err(untrusted(format!("{} is a symlink", path))),
+                _ => format!("Couldn't open {}: {}", path, os_err),
+            };
+            journal::security_event(&err);
+            return Err(From::from(err));
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        Ok(Self { file, path, owner })
+    }
+    /// Verify the type, the owner and, if given, the permissions of the opened file.
+    /// Any difference is reported as a security event, as someone else has put it there
+    fn verify(&self, file_type: u32, mode: Option<u32>) -> Result<(), Box<dyn Error>> {
+        let metadata = self.file.metadata()?;
+        let actual_mode = metadata.mode() & 0o7777;
+        let err = if metadata.mode() & S_IFMT != file_type {
+            format!("{} has the wrong file type", self.path)
+        } else if metadata.uid() != self.owner {
+            format!(
+                "{} is owned by {} instead of {}",
+                self.path,
+                metadata.uid(),
+                self.owner
+            )
+        } else if mode.map_or(false, |expected| expected != actual_mode) {
+            format!("{} has the wrong mode {:o}", self.path, actual_mode)
+        } else {
+            return Ok(());
+        };
+        Err(untrusted(err))
+    }
+}
+
+/// Function to report a file that Rudo didn't put there as a security event, and return its error
+fn untrusted(message: String) -> Box<dyn Error> {
+    journal::security_event(&message);
+    Box::new(UntrustedFile { message })
+}
+
+/// Transform a name to a C string, refusing anything that isn't a single component of a path
+fn file_name(name: &str) -> Result<CString, Box<dyn Error>> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(From::from(format!("{} is not a valid file name", name)));
+    }
+    Ok(CString::new(name)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, DirBuilder},
+        os::unix::fs::{symlink, DirBuilderExt},
+    };
+
+    use libc::{flock, LOCK_EX, LOCK_NB};
+
+    use super::{AsRawFd, Dir, Error, Path, Permissions, PermissionsExt};
+    use crate::utils;
+
+    /// Open the directory of a test, that belong to the user running the tests
+    fn open_test_dir(path: &Path) -> Result<Dir, Box<dyn Error>> {
+        Dir::open(path, users::get_effective_uid())
+    }
+
+    #[test]
+    fn test_write_read_file() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_write")?;
+        let dir = open_test_dir(&path)?.open_or_create_dir("user", 0o700)?;
+        dir.write_file("token", 0o600, b"1234")?;
+        dir.write_file("token", 0o600, b"5678")?;
+        let data = dir.read_file("token", 0o600)?;
+        let missing = dir.read_file("missing", 0o600)?;
+        fs::remove_dir_all(&path)?;
+        if data == Some(b"5678".to_vec()) && missing.is_none() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to write and read back the file"))
+        }
+    }
+
+    #[test]
+    fn test_entries() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_entries")?;
+        let dir = open_test_dir(&path)?;
+        dir.write_file("first", 0o600, b"1234")?;
+        dir.write_file("second", 0o600, b"5678")?;
+        dir.remove_file("second")?;
+        let entries = dir.entries()?;
+        fs::remove_dir_all(&path)?;
+        if entries == vec![String::from("first")] {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to list the entries of the directory",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_lock() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_lock")?;
+        let dir = open_test_dir(&path)?;
+        let other = open_test_dir(&path)?;
+        dir.lock()?;
+        // Another opening of the directory, like another invocation, can't take the lock
+        let busy = unsafe { flock(other.file.as_raw_fd(), LOCK_EX | LOCK_NB) } != 0;
+        drop(dir);
+        let free = unsafe { flock(other.file.as_raw_fd(), LOCK_EX | LOCK_NB) } == 0;
+        fs::remove_dir_all(&path)?;
+        if busy && free {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to lock the directory"))
+        }
+    }
+
+    #[test]
+    fn test_symlink_dir() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_symlink_dir")?;
+        let dir = open_test_dir(&path)?;
+        DirBuilder::new().mode(0o700).create(path.join("target"))?;
+        symlink("target", path.join("user"))?;
+        let result = dir.open_or_create_dir("user", 0o700);
+        fs::remove_dir_all(&path)?;
+        if result.is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: a symlink to a directory was followed",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_symlink_file() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_symlink_file")?;
+        let dir = open_test_dir(&path)?;
+        dir.write_file("target", 0o600, b"1234")?;
+        symlink("target", path.join("token"))?;
+        let read = dir.read_file("token", 0o600);
+        dir.write_file("token", 0o600, b"5678")?;
+        let target = dir.read_file("target", 0o600)?;
+        let is_symlink = fs::symlink_metadata(path.join("token"))?
+            .file_type()
+            .is_symlink();
+        fs::remove_dir_all(&path)?;
+        if read.is_err() && target == Some(b"1234".to_vec()) && !is_symlink {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a symlink to a file was followed"))
+        }
+    }
+
+    #[test]
+    fn test_precreated_dir() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_precreated")?;
+        let dir = open_test_dir(&path)?;
+        DirBuilder::new().mode(0o777).create(path.join("user"))?;
+        fs::set_permissions(path.join("user"), Permissions::from_mode(0o777))?;
+        let result = dir.open_or_create_dir("user", 0o700);
+        fs::remove_dir_all(&path)?;
+        if result.is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: a pre-created directory was accepted",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_invalid_name() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("dir_invalid_name")?;
+        let dir = open_test_dir(&path)?;
+        let result = dir.open_or_create_dir("../escape", 0o700);
+        fs::remove_dir_all(&path)?;
+        if result.is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a name with a slash was accepted"))
+        }
+    }
+}
@@ -0,0 +1,209 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// The exit code of Rudo for every error that doesn't have a specific one
+pub static DEFAULT_EXIT_CODE: i32 = 1;
+
+/// `RudoError` are the errors that the caller of Rudo must be able to recognize by their exit code.
+/// The exit codes are stable, a wrapper can rely on them
+#[derive(Debug, Clone, PartialEq)]
+pub enum RudoError {
+    /// The configuration file couldn't be parsed, exit code 2
+    ConfigParse(String),
+    /// A password is required, but Rudo was told to not ask for it, exit code 3
+    PasswordRequired,
+    /// The user isn't authorized to use Rudo by the configuration, exit code 4
+    NotAuthorized(String),
+    /// The user isn't a member of the group of the configuration, exit code 5
+    GroupMismatch(String),
+    /// The authentication failed, like a wrong password or a locked account, exit code 6
+    AuthFailed(String),
+    /// The user made too many authentication failures and is locked out of Rudo, exit code 7
+    LockedOut(String),
+    /// Rudo needs a terminal, but the standard input isn't one, exit code 8
+    NoTty,
+    /// The session token can't be reused, exit code 9
+    TokenInvalid(String),
+    /// The command was run, but it failed or was killed, exit code 10
+    ChildFailed(String),
+    /// The user is authorized, but not to run this command, exit code 11
+    CommandNotAllowed(String),
+    /// The sha256 digest of the program isn't the one of its command rule, exit code 12
+    DigestMismatch(String),
+    /// The rule of the user doesn't allow to impersonate the target user, exit code 13
+    TargetNotAllowed(String),
+    /// The rule of the user doesn't apply on this host, exit code 14
+    HostNotAllowed(String),
+    /// The rule of the user doesn't allow commands at this time, exit code 15
+    OutsideAllowedTimes(String),
+    /// The command exist, but it can't be executed, exit code 126
+    CommandNotExecutable(String),
+    /// The command doesn't exist, exit code 127
+    CommandNotFound(String),
+}
+
+impl RudoError {
+    /// The exit code that correspond to the error
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ConfigParse(_) => 2,
+            Self::PasswordRequired => 3,
+            Self::NotAuthorized(_) => 4,
+            Self::GroupMismatch(_) => 5,
+            Self::AuthFailed(_) => 6,
+            Self::LockedOut(_) => 7,
+            Self::NoTty => 8,
+            Self::TokenInvalid(_) => 9,
+            Self::ChildFailed(_) => 10,
+            Self::CommandNotAllowed(_) => 11,
+            Self::DigestMismatch(_) => 12,
+            Self::TargetNotAllowed(_) => 13,
+            Self::HostNotAllowed(_) => 14,
+            Self::OutsideAllowedTimes(_) => 15,
+            Self::CommandNotExecutable(_) => 126,
+            Self::CommandNotFound(_) => 127,
+        }
+    }
+}
+
+impl Display for RudoError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConfigParse(err) => {
+                write!(formatter, "The configuration file is invalid: {}", err)
+            }
+            Self::PasswordRequired => {
+                write!(
+                    formatter,
+                    "A password is required, but Rudo is non-interactive"
+                )
+            }
+            Self::NotAuthorized(user) => write!(
+                formatter,
+                "{} is not authorized to use Rudo! Will be report to administrator!",
+                user
+            ),
+            Self::GroupMismatch(group) => write!(
+                formatter,
+                "User is not a member of the authorized group: {}",
+                group
+            ),
+            Self::AuthFailed(reason) | Self::LockedOut(reason) | Self::TokenInvalid(reason) => {
+                write!(formatter, "{}", reason)
+            }
+            Self::NoTty => write!(formatter, "Rudo must be called from a terminal!"),
+            Self::ChildFailed(status) => write!(formatter, "The command {}", status),
+            Self::CommandNotAllowed(command) => write!(
+                formatter,
+                "The command {} isn't allowed by the configuration",
+                command
+            ),
+            Self::DigestMismatch(program) => write!(
+                formatter,
+                "The sha256 digest of {} isn't the one of the configuration, it was modified",
+                program
+            ),
+            Self::TargetNotAllowed(target) => write!(
+                formatter,
+                "The configuration doesn't allow to run commands as {}",
+                target
+            ),
+            Self::HostNotAllowed(host) => write!(
+                formatter,
+                "The configuration doesn't allow to run commands on {}",
+                host
+            ),
+            Self::OutsideAllowedTimes(times) => write!(
+                formatter,
+                "The configuration only allow to run commands {}",
+                times
+            ),
+            Self::CommandNotExecutable(program) => {
+                write!(formatter, "{} can't be executed", program)
+            }
+            Self::CommandNotFound(program) => write!(formatter, "{} doesn't exist", program),
+        }
+    }
+}
+
+impl Error for RudoError {}
+
+#[must_use]
+/// Function to find the exit code of any error, `DEFAULT_EXIT_CODE` if it's not a `RudoError`
+pub fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    match err.downcast_ref::<RudoError>() {
+        Some(rudo_error) => rudo_error.exit_code(),
+        None => DEFAULT_EXIT_CODE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_code, Error, RudoError, DEFAULT_EXIT_CODE};
+
+    #[test]
+    fn test_exit_code() -> Result<(), Box<dyn Error>> {
+        let rudo_error: Box<dyn Error> = Box::new(RudoError::PasswordRequired);
+        let other_error: Box<dyn Error> = From::from("Other error");
+        if exit_code(rudo_error.as_ref()) == 3
+            && exit_code(other_error.as_ref()) == DEFAULT_EXIT_CODE
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to find the exit code of the error"))
+        }
+    }
+
+    #[test]
+    fn test_exit_code_distinct() -> Result<(), Box<dyn Error>> {
+        let errors = vec![
+            RudoError::ConfigParse(String::new()),
+            RudoError::PasswordRequired,
+            RudoError::NotAuthorized(String::new()),
+            RudoError::GroupMismatch(String::new()),
+            RudoError::AuthFailed(String::new()),
+            RudoError::LockedOut(String::new()),
+            RudoError::NoTty,
+            RudoError::TokenInvalid(String::new()),
+            RudoError::ChildFailed(String::new()),
+            RudoError::CommandNotAllowed(String::new()),
+            RudoError::DigestMismatch(String::new()),
+            RudoError::TargetNotAllowed(String::new()),
+            RudoError::HostNotAllowed(String::new()),
+            RudoError::OutsideAllowedTimes(String::new()),
+            RudoError::CommandNotExecutable(String::new()),
+            RudoError::CommandNotFound(String::new()),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(RudoError::exit_code).collect();
+        codes.push(DEFAULT_EXIT_CODE);
+        codes.sort_unstable();
+        codes.dedup();
+        if codes.len() == errors.len().saturating_add(1) {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: two errors share the same exit code",
+            ))
+        }
+    }
+}
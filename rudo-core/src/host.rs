@@ -0,0 +1,299 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    ffi::{CStr, CString},
+    fmt, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ptr,
+};
+
+use libc::{
+    addrinfo, freeaddrinfo, freeifaddrs, getaddrinfo, getifaddrs, ifaddrs, sockaddr, sockaddr_in,
+    sockaddr_in6, AF_INET, AF_INET6, AI_CANONNAME,
+};
+
+use crate::{policy, utils};
+
+/// `Host` is how the rules know the machine: its names and the addresses of its interfaces
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Host {
+    /// The hostname, as given by the kernel
+    pub name: String,
+    /// The fully qualified domain name, if it was resolved
+    pub fqdn: Option<String>,
+    /// The IP addresses of the local interfaces
+    pub addresses: Vec<IpAddr>,
+}
+
+impl Host {
+    /// Function to find the names and the addresses of the machine.
+    /// The FQDN is only resolved if it's asked, as it can query the DNS
+    ///
+    /// # Errors
+    /// Return an error if the hostname or the addresses of the interfaces couldn't be read
+    pub fn local(resolve_fqdn: bool) -> Result<Self, Box<dyn Error>> {
+        let name = utils::hostname()?;
+        let fqdn = if resolve_fqdn {
+            canonical_name(&name)
+        } else {
+            None
+        };
+        Ok(Self {
+            name,
+            fqdn,
+            addresses: interface_addresses()?,
+        })
+    }
+    /// Function to know if a host pattern of a rule designate the machine. The pattern is an IP address
+    /// or a network in the CIDR notation, that match an address of an interface, or else a glob,
+    /// where `*` match any characters, that match the hostname, its first label or the FQDN
+    #[must_use]
+    pub fn matches(&self, pattern: &str) -> bool {
+        match parse_network(pattern) {
+            Some(Ok((network, prefix))) => self
+                .addresses
+                .iter()
+                .any(|address| in_network(*address, network, prefix)),
+            // An invalid network is refused when the configuration is read, it match nothing there
+            Some(Err(_)) => false,
+            None => {
+                let glob = pattern.to_lowercase();
+                let short_name = self.name.split('.').next().unwrap_or_default();
+                [
+                    Some(self.name.as_str()),
+                    Some(short_name),
+                    self.fqdn.as_deref(),
+                ]
+                .iter()
+                .flatten()
+                .any(|name| policy::glob_matches(&glob, &name.to_lowercase()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.fqdn.as_ref().unwrap_or(&self.name))
+    }
+}
+
+/// A parsed network, as its address and the length of its prefix, or why it's invalid
+type Network = Result<(IpAddr, u32), Box<dyn Error>>;
+
+/// Function to verify that a host pattern of a rule that look like a network is a valid one
+///
+/// # Errors
+/// Return an error if the address or the length of the prefix is invalid
+pub fn validate_pattern(pattern: &str) -> Result<(), Box<dyn Error>> {
+    match parse_network(pattern) {
+        Some(Err(err)) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Function to parse a pattern as an IP address, or a network with the length of its prefix.
+/// A pattern without `/` that isn't an address is a name
+fn parse_network(pattern: &str) -> Option<Network> {
+    let mut parts = pattern.splitn(2, '/');
+    let text = parts.next().unwrap_or_default();
+    let prefix = parts.next();
+    let parsed = match (text.parse::<IpAddr>(), prefix) {
+        (Ok(parsed), _) => parsed,
+        (Err(_), None) => return None,
+        (Err(_), Some(_)) => {
+            return Some(Err(From::from(format!(
+                "invalid network {}, the address isn't an IP address",
+                pattern
+            ))))
+        }
+    };
+    let max_prefix = if parsed.is_ipv4() { 32 } else { 128 };
+    let length = match prefix.map(str::parse::<u32>) {
+        None => max_prefix,
+        Some(Ok(length)) if length <= max_prefix => length,
+        Some(_) => {
+            return Some(Err(From::from(format!(
+                "invalid network {}, the prefix must be between 0 and {}",
+                pattern, max_prefix
+            ))))
+        }
+    };
+    Some(Ok((parsed, length)))
+}
+
+/// Function to know if an address is in a network, an IPv4 address is never in an IPv6 network
+fn in_network(address: IpAddr, network: IpAddr, prefix: u32) -> bool {
+    match (address, network) {
+        (IpAddr::V4(address_v4), IpAddr::V4(network_v4)) => {
+            let mask = u32::MAX
+                .checked_shl(32_u32.saturating_sub(prefix))
+                .unwrap_or(0);
+            u32::from(address_v4) & mask == u32::from(network_v4) & mask
+        }
+        (IpAddr::V6(address_v6), IpAddr::V6(network_v6)) => {
+            let mask = u128::MAX
+                .checked_shl(128_u32.saturating_sub(prefix))
+                .unwrap_or(0);
+            u128::from(address_v6) & mask == u128::from(network_v6) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Safe wrapper to resolve the fully qualified domain name of the host, none if it can't be resolved
+fn canonical_name(name: &str) -> Option<String> {
+    let node = CString::new(name).ok()?;
+    let mut hints: addrinfo = unsafe { mem::zeroed() };
+    hints.ai_flags = AI_CANONNAME;
+    let mut result: *mut addrinfo = ptr::null_mut();
+    if unsafe { getaddrinfo(node.as_ptr(), ptr::null(), &hints, &mut result) } != 0 {
+        return None;
+    }
+    // Only the first result has the canonical name
+    let fqdn = unsafe { result.as_ref() }
+        .map(|info| info.ai_canonname)
+        .filter(|canonname| !canonname.is_null())
+        .and_then(|canonname| unsafe { CStr::from_ptr(canonname) }.to_str().ok())
+        .map(str::to_owned);
+    unsafe { freeaddrinfo(result) };
+    fqdn
+}
+
+/// Safe wrapper to read the IP addresses of the local interfaces
+fn interface_addresses() -> Result<Vec<IpAddr>, Box<dyn Error>> {
+    let mut interfaces: *mut ifaddrs = ptr::null_mut();
+    if unsafe { getifaddrs(&mut interfaces) } != 0 {
+        return Err(From::from("Couldn't read the addresses of the interfaces"));
+    }
+    let mut addresses = Vec::new();
+    let mut interface = interfaces;
+    while let Some(current) = unsafe { interface.as_ref() } {
+        addresses.extend(ip_address(current.ifa_addr));
+        interface = current.ifa_next;
+    }
+    unsafe { freeifaddrs(interfaces) };
+    Ok(addresses)
+}
+
+/// Function to convert the address of an interface to an IP address, none if it isn't one
+fn ip_address(address: *const sockaddr) -> Option<IpAddr> {
+    let family = i32::from(unsafe { address.as_ref() }?.sa_family);
+    if family == AF_INET {
+        let inet = unsafe { ptr::read_unaligned(address.cast::<sockaddr_in>()) };
+        Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+            inet.sin_addr.s_addr,
+        ))))
+    } else if family == AF_INET6 {
+        let inet6 = unsafe { ptr::read_unaligned(address.cast::<sockaddr_in6>()) };
+        Some(IpAddr::V6(Ipv6Addr::from(inet6.sin6_addr.s6_addr)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, net::IpAddr};
+
+    use super::{validate_pattern, Host};
+
+    /// Create the host of the tests, with an injected name and interfaces
+    fn test_host() -> Result<Host, Box<dyn Error>> {
+        Ok(Host {
+            name: String::from("web1"),
+            fqdn: Some(String::from("web1.example.com")),
+            addresses: vec![
+                "127.0.0.1".parse::<IpAddr>()?,
+                "10.1.2.3".parse::<IpAddr>()?,
+                "fd00::12".parse::<IpAddr>()?,
+            ],
+        })
+    }
+
+    #[test]
+    fn test_matches() -> Result<(), Box<dyn Error>> {
+        let host = test_host()?;
+        let table = vec![
+            ("web1", true),
+            ("web*", true),
+            ("WEB1.example.com", true),
+            ("*.example.com", true),
+            ("db*", false),
+            ("web1.example.org", false),
+            ("10.1.2.3", true),
+            ("10.0.0.0/8", true),
+            ("10.1.3.0/24", false),
+            ("0.0.0.0/0", true),
+            ("fd00::/8", true),
+            ("fe80::/10", false),
+            ("192.168.0.1", false),
+        ];
+        for (pattern, expected) in table {
+            if host.matches(pattern) != expected {
+                return Err(From::from(format!(
+                    "Test failed: {} should be {}",
+                    pattern, expected
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_name() -> Result<(), Box<dyn Error>> {
+        let host = Host {
+            name: String::from("db2.example.com"),
+            ..Host::default()
+        };
+        if host.matches("db*") && host.matches("db2") && !host.matches("10.0.0.0/8") {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to match the first label of the hostname",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_validate_pattern() -> Result<(), Box<dyn Error>> {
+        if validate_pattern("web*").is_ok()
+            && validate_pattern("10.0.0.0/8").is_ok()
+            && validate_pattern("fd00::/128").is_ok()
+            && validate_pattern("10.0.0.0/33").is_err()
+            && validate_pattern("fd00::/129").is_err()
+            && validate_pattern("web/8").is_err()
+            && validate_pattern("10.0.0.0/x").is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to validate the networks"))
+        }
+    }
+
+    #[test]
+    fn test_local() -> Result<(), Box<dyn Error>> {
+        let host = Host::local(false)?;
+        if !host.name.is_empty() && host.fqdn.is_none() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to read the local host"))
+        }
+    }
+}
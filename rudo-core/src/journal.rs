@@ -17,15 +17,53 @@
  */
 use std::error::Error;
 
-use log::{info, LevelFilter};
+use log::{error, info, LevelFilter};
 #[cfg(all(target_os = "macos", feature = "macos"))]
 use oslog::OsLogger;
 #[cfg(all(target_os = "linux", feature = "journald"))]
 use systemd::journal;
 
+/// The target of the logs that an administrator must investigate, like the tampering of a token
+static SECURITY_TARGET: &str = "rudo::security";
+
+/// Function to log a security event, so it can be filtered from the rest of the logs
+pub(crate) fn security_event(message: &str) {
+    error!(target: SECURITY_TARGET, "Security event: {}", message);
+}
+
+#[cfg(all(target_os = "linux", feature = "journald"))]
+/// Function to log an audit event with its fields, like `RUDO_ATTEMPTS`, so journald can index them
+pub fn audit_event(message: &str, fields: &[(&str, &str)]) {
+    let mut entries = vec![
+        format!("MESSAGE={}", message),
+        String::from("PRIORITY=3"),
+        String::from("SYSLOG_IDENTIFIER=rudo"),
+    ];
+    for (key, value) in fields {
+        entries.push(format!("{}={}", key, value));
+    }
+    let args: Vec<&str> = entries.iter().map(String::as_str).collect();
+    if journal::send(&args) != 0 {
+        error!("{}", message);
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "journald")))]
+/// Function to log an audit event with its fields, written as `KEY=value` after the message
+pub fn audit_event(message: &str, fields: &[(&str, &str)]) {
+    let mut entry = String::from(message);
+    for (key, value) in fields {
+        entry.push_str(&format!(" {}={}", key, value));
+    }
+    error!("{}", entry);
+}
+
 #[cfg(all(target_os = "linux", feature = "journald"))]
 /// Function to decide the maximum level of logging that journald will receive with the user supply option
-pub(crate) fn log_journald(debug: bool) -> Result<(), Box<dyn Error>> {
+///
+/// # Errors
+/// Return an error if the logger couldn't be initialized
+pub fn log_journald(debug: bool) -> Result<(), Box<dyn Error>> {
     // Initialize Logs with journald
     if let Ok(()) = journal::JournalLog::init() {
         // Determine the maximum level of log the user want
@@ -44,7 +82,10 @@ pub(crate) fn log_journald(debug: bool) -> Result<(), Box<dyn Error>> {
 
 #[cfg(all(target_os = "macos", feature = "macos"))]
 /// Function to decide the maximum level of logging that oslog server will receive with the user supply option
-pub(crate) fn log_oslog(debug: bool) -> Result<(), Box<dyn Error>> {
+///
+/// # Errors
+/// Return an error if the logger couldn't be initialized
+pub fn log_oslog(debug: bool) -> Result<(), Box<dyn Error>> {
     if debug {
         OsLogger::new("com.github.rudo")
             .level_filter(LevelFilter::Debug)
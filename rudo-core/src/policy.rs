@@ -0,0 +1,709 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{error::Error, ffi::OsString, path::PathBuf, time::SystemTime};
+
+use crate::{
+    alias::Aliases,
+    config::{self, CommandRule, Config, PasswordConf, UserConf, Validity},
+    error::RudoError,
+    host::Host,
+    schedule::{self, AllowedTimes},
+};
+
+/// `Request` is everything the policy needs to know about what the user ask Rudo
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The Unix username of the user that invoke Rudo
+    pub user: String,
+    /// The Unix groups the user is a member of
+    pub groups: Vec<String>,
+    /// The names and the addresses of the machine
+    pub host: Host,
+    /// The Unix username of the user to impersonate
+    pub target: String,
+    /// The program to run
+    pub command: String,
+    /// The arguments of the program
+    pub args: Vec<String>,
+    /// The working directory of the user
+    pub cwd: PathBuf,
+    /// The time of the request
+    pub time: SystemTime,
+}
+
+/// `Verdict` is the answer of the policy, a denial has the error that explain it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// The request is allowed
+    Allow,
+    /// The request is denied
+    Deny(RudoError),
+}
+
+/// `EnvRules` is what the environment of the command must be
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvRules {
+    /// The environment of the user is removed from the command
+    pub reset: bool,
+    /// The variables that are kept when the environment is removed
+    pub keep: Vec<String>,
+}
+
+impl EnvRules {
+    /// Function to filter the variables of the environment of the user that the command can have
+    pub fn filter<I>(&self, vars: I) -> Vec<(OsString, OsString)>
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        vars.into_iter()
+            .filter(|(name, _)| {
+                !self.reset
+                    || self
+                        .keep
+                        .iter()
+                        .any(|keep| name.as_os_str() == keep.as_str())
+            })
+            .collect()
+    }
+}
+
+/// `Decision` is the answer of the policy to a `Request`, with everything Rudo must enforce
+#[derive(Debug)]
+pub struct Decision {
+    /// The request is allowed or denied
+    pub verdict: Verdict,
+    /// Why the request is allowed or denied
+    pub reason: String,
+    /// The index of the rule of the configuration that matched the user, none if the default rule was used
+    pub rule: Option<usize>,
+    /// The rules of the user that were ignored, as they aren't in effect, from the last one
+    pub inactive: Vec<(usize, Validity)>,
+    /// The index of the command rule of the user that matched the command, after its aliases are expanded,
+    /// none if the user has none
+    pub command_rule: Option<usize>,
+    /// The sha256 digest the program must have, as given by its command rule
+    pub digest: Option<String>,
+    /// How the user must authenticate
+    pub auth: PasswordConf,
+    /// What the environment of the command must be
+    pub env: EnvRules,
+}
+
+impl Decision {
+    /// Function to know if the request is allowed
+    #[must_use]
+    pub fn is_allowed(&self) -> bool {
+        self.verdict == Verdict::Allow
+    }
+    /// Function to turn a denial into the error that explain it
+    ///
+    /// # Errors
+    /// Return the error of the denial if the request is denied
+    pub fn enforce(&self) -> Result<(), RudoError> {
+        match self.verdict {
+            Verdict::Allow => Ok(()),
+            Verdict::Deny(ref err) => Err(err.clone()),
+        }
+    }
+}
+
+/// Function that decide if a request is allowed by the configuration, and how it must be done.
+/// It's pure, the same request and configuration always give the same decision
+#[must_use]
+pub fn evaluate(conf: &Config, request: &Request) -> Decision {
+    // The last rule of the user in effect has the priority, without one the default rule apply
+    let now = schedule::unix_time(request.time).unwrap_or_default();
+    let mut inactive = Vec::new();
+    let rule = conf
+        .user
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|&(_, userconf)| conf.aliases.user_matches(&userconf.username, &request.user))
+        .find(|&(index, userconf)| match userconf.validity(now) {
+            Validity::Active => true,
+            validity => {
+                inactive.push((index, validity));
+                false
+            }
+        })
+        .map(|(index, _)| index);
+    let userconf = rule
+        .and_then(|index| conf.user.get(index))
+        .cloned()
+        .unwrap_or_default();
+    let matched_rule = rule.map_or_else(
+        || String::from("the default rule"),
+        |index| format!("the rule {} of {}", index, userconf.username),
+    );
+    let rule_name = if inactive.is_empty() {
+        matched_rule
+    } else {
+        let ignored: Vec<String> = inactive.iter().map(describe_inactive).collect();
+        format!("{}, as {}", matched_rule, ignored.join(" and "))
+    };
+
+    let scope = expand_scope(&conf.aliases, &userconf);
+    let commands = scope
+        .as_ref()
+        .map_or(&[][..], |expanded| &expanded.commands);
+    let command_rule = commands
+        .iter()
+        .position(|command| command_matches(command, request));
+
+    let verdict = if rule.is_none() && userconf.username != request.user {
+        Verdict::Deny(RudoError::NotAuthorized(request.user.clone()))
+    } else if !request.groups.contains(&userconf.group) {
+        Verdict::Deny(RudoError::GroupMismatch(userconf.group.clone()))
+    } else {
+        match scope {
+            // An invalid alias is refused when the configuration is read, it allow nothing there
+            Err(ref err) => Verdict::Deny(RudoError::ConfigParse(err.to_string())),
+            Ok(ref expanded) => scope_verdict(expanded, request, command_rule),
+        }
+    };
+    let reason = match verdict {
+        Verdict::Allow => format!(
+            "{} may run {} as {} by {}",
+            request.user,
+            command_line(request),
+            request.target,
+            rule_name
+        ),
+        Verdict::Deny(ref err) => format!("Denied by {}: {}", rule_name, err),
+    };
+
+    Decision {
+        verdict,
+        reason,
+        rule,
+        inactive,
+        command_rule,
+        digest: command_rule
+            .and_then(|index| commands.get(index))
+            .and_then(|command| command.digest.clone()),
+        auth: conf.rudo.password_conf(&userconf),
+        env: env_rules(&userconf),
+    }
+}
+
+/// Function to describe a rule that was ignored, as it isn't in effect
+fn describe_inactive(&(index, validity): &(usize, Validity)) -> String {
+    match validity {
+        Validity::Expired => format!("the rule {} expired", index),
+        Validity::Pending => format!("the rule {} isn't in effect yet", index),
+        Validity::Active => format!("the rule {} is in effect", index),
+    }
+}
+
+/// `Scope` is what a rule allow, with its aliases expanded
+struct Scope {
+    /// The command rules
+    commands: Vec<CommandRule>,
+    /// The users to impersonate
+    runas: Vec<String>,
+    /// The host patterns
+    hosts: Vec<String>,
+    /// The windows of the week when commands are allowed
+    allowed_times: Option<AllowedTimes>,
+}
+
+/// Function to expand the aliases of what a rule allow
+fn expand_scope(aliases: &Aliases, userconf: &UserConf) -> Result<Scope, Box<dyn Error>> {
+    Ok(Scope {
+        commands: aliases.expand_commands(&userconf.commands)?,
+        runas: aliases.expand_runas(&userconf.runas)?,
+        hosts: aliases.expand_hosts(&userconf.hosts)?,
+        allowed_times: userconf.allowed_times.clone(),
+    })
+}
+
+/// Function to verify the host, the target, the command and the time of the request, an empty list allow everything
+fn scope_verdict(scope: &Scope, request: &Request, command_rule: Option<usize>) -> Verdict {
+    if !scope.hosts.is_empty() && !scope.hosts.iter().any(|host| request.host.matches(host)) {
+        Verdict::Deny(RudoError::HostNotAllowed(request.host.to_string()))
+    } else if !scope.runas.is_empty() && !scope.runas.contains(&request.target) {
+        Verdict::Deny(RudoError::TargetNotAllowed(request.target.clone()))
+    } else if !scope.commands.is_empty() && command_rule.is_none() {
+        Verdict::Deny(RudoError::CommandNotAllowed(command_line(request)))
+    } else {
+        scope
+            .allowed_times
+            .as_ref()
+            .map_or(Verdict::Allow, |allowed_times| {
+                time_verdict(allowed_times, request.time)
+            })
+    }
+}
+
+/// Function to verify that the time of the request is in a window, the denial say which windows apply
+fn time_verdict(allowed_times: &AllowedTimes, time: SystemTime) -> Verdict {
+    // A time before the epoch is never in a window
+    let seconds = schedule::unix_time(time);
+    match seconds {
+        Some(now) if allowed_times.allows(now) => Verdict::Allow,
+        Some(now) => Verdict::Deny(RudoError::OutsideAllowedTimes(format!(
+            "{}, it's {} there",
+            allowed_times,
+            allowed_times.local_time(now)
+        ))),
+        None => Verdict::Deny(RudoError::OutsideAllowedTimes(allowed_times.to_string())),
+    }
+}
+
+/// Function to write the command of the request as it would be typed
+#[must_use]
+pub fn command_line(request: &Request) -> String {
+    let mut command = vec![request.command.as_str()];
+    command.extend(request.args.iter().map(String::as_str));
+    command.join(" ")
+}
+
+/// Function to verify that the command of the request is the one of the rule, with allowed arguments
+fn command_matches(rule: &CommandRule, request: &Request) -> bool {
+    if rule.path != request.command {
+        return false;
+    }
+    match rule.args {
+        // Without patterns, any argument is allowed
+        None => true,
+        // Every argument must match the pattern at its position, an empty list allow no argument
+        Some(ref patterns) => {
+            patterns.len() == request.args.len()
+                && patterns
+                    .iter()
+                    .zip(&request.args)
+                    .all(|(pattern, arg)| arg_matches(pattern, arg))
+        }
+    }
+}
+
+/// Function to match an argument with its pattern, an anchored regex, a glob or the exact argument
+fn arg_matches(pattern: &str, arg: &str) -> bool {
+    match config::arg_regex(pattern) {
+        // An invalid regex is refused when the configuration is read, it never match there
+        Some(compiled) => compiled.map_or(false, |regex| regex.is_match(arg)),
+        None if pattern.contains('*') => glob_matches(pattern, arg),
+        None => pattern == arg,
+    }
+}
+
+/// Function to match a text with a glob, where `*` match any characters, including none
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = match (parts.first(), parts.last()) {
+        (Some(first), Some(last)) if parts.len() >= 2 => (*first, *last),
+        _ => return pattern == text,
+    };
+    // The first part is at the start and the last part at the end, without overlapping
+    let end = text.len().saturating_sub(last.len());
+    if !text.starts_with(first)
+        || !text.ends_with(last)
+        || first.len().saturating_add(last.len()) > text.len()
+    {
+        return false;
+    }
+    // The parts between the stars must be found in order, the first occurrence is always the best
+    let mut position = first.len();
+    for part in parts.iter().skip(1).take(parts.len().saturating_sub(2)) {
+        match text.get(position..end).and_then(|middle| middle.find(part)) {
+            Some(index) => position = position.saturating_add(index).saturating_add(part.len()),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Function to extract the rules of the environment of a rule
+fn env_rules(userconf: &UserConf) -> EnvRules {
+    EnvRules {
+        reset: userconf.env_reset,
+        keep: userconf.env_keep.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        error::Error,
+        ffi::OsString,
+        path::PathBuf,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{evaluate, glob_matches, EnvRules, Request, Verdict};
+    use crate::{
+        config::{AuthAs, CommandRule, Config, UserConf, Validity},
+        error::RudoError,
+        host::Host,
+    };
+
+    /// Create the configuration of the tests, alice is authorized if she is a member of wheel
+    fn test_conf() -> Config {
+        let mut conf = Config::default();
+        conf.user.push(UserConf {
+            username: String::from("alice"),
+            group: String::from("wheel"),
+            auth_as: AuthAs::Target,
+            env_reset: true,
+            env_keep: vec![String::from("TERM")],
+            ..UserConf::default()
+        });
+        conf
+    }
+
+    /// Create a request of the tests
+    fn test_request(user: &str, groups: &[&str]) -> Request {
+        Request {
+            user: user.to_owned(),
+            groups: groups.iter().map(|&group| group.to_owned()).collect(),
+            host: Host {
+                name: String::from("localhost"),
+                ..Host::default()
+            },
+            target: String::from("root"),
+            command: String::from("/usr/bin/id"),
+            args: Vec::new(),
+            cwd: PathBuf::from("/"),
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<(), Box<dyn Error>> {
+        let conf = test_conf();
+        let table = vec![
+            ("alice", vec!["wheel"], Verdict::Allow),
+            ("root", vec!["wheel", "root"], Verdict::Allow),
+            (
+                "alice",
+                vec!["users"],
+                Verdict::Deny(RudoError::GroupMismatch(String::from("wheel"))),
+            ),
+            (
+                "bob",
+                vec!["wheel"],
+                Verdict::Deny(RudoError::NotAuthorized(String::from("bob"))),
+            ),
+        ];
+        for (user, groups, verdict) in table {
+            let decision = evaluate(&conf, &test_request(user, &groups));
+            if decision.verdict != verdict {
+                return Err(From::from(format!(
+                    "Test failed: {} in {:?} got {:?}",
+                    user, groups, decision.verdict
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_rule() -> Result<(), Box<dyn Error>> {
+        let decision = evaluate(&test_conf(), &test_request("alice", &["wheel"]));
+        if decision.is_allowed()
+            && decision.enforce().is_ok()
+            && decision.rule == Some(1)
+            && decision.auth.auth_as == AuthAs::Target
+            && decision.env.reset
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to use the rule of the user"))
+        }
+    }
+
+    #[test]
+    fn test_evaluate_default_rule() -> Result<(), Box<dyn Error>> {
+        let decision = evaluate(&test_conf(), &test_request("bob", &["wheel"]));
+        if decision.rule.is_none()
+            && decision.enforce() == Err(RudoError::NotAuthorized(String::from("bob")))
+            && !decision.env.reset
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to use the default rule"))
+        }
+    }
+
+    #[test]
+    fn test_env_rules() -> Result<(), Box<dyn Error>> {
+        let vars = vec![
+            (OsString::from("TERM"), OsString::from("xterm")),
+            (OsString::from("LD_PRELOAD"), OsString::from("evil.so")),
+        ];
+        let reset = EnvRules {
+            reset: true,
+            keep: vec![String::from("TERM")],
+        };
+        if reset.filter(vars.clone()) == vec![(OsString::from("TERM"), OsString::from("xterm"))]
+            && EnvRules::default().filter(vars.clone()) == vars
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to filter the environment"))
+        }
+    }
+
+    #[test]
+    fn test_glob_matches() -> Result<(), Box<dyn Error>> {
+        let table = vec![
+            ("app-*", "app-web", true),
+            ("app-*", "app-", true),
+            ("app-*", "db-web", false),
+            ("*.service", "nginx.service", true),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "axxbyyc", true),
+            ("a*b*c", "acb", false),
+            ("ab*ba", "aba", false),
+            ("*", "", true),
+        ];
+        for (pattern, text, expected) in table {
+            if glob_matches(pattern, text) != expected {
+                return Err(From::from(format!(
+                    "Test failed: {} with {} should be {}",
+                    pattern, text, expected
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_command() -> Result<(), Box<dyn Error>> {
+        let mut conf = test_conf();
+        if let Some(alice) = conf.user.get_mut(1) {
+            alice.commands = vec![
+                CommandRule {
+                    path: String::from("/usr/bin/systemctl"),
+                    args: Some(vec![String::from("restart"), String::from("app-*")]),
+                    digest: None,
+                },
+                CommandRule {
+                    path: String::from("/usr/bin/journalctl"),
+                    args: Some(vec![String::from("-u"), String::from("^(app|web)-[0-9]+$")]),
+                    digest: None,
+                },
+                CommandRule {
+                    path: String::from("/usr/bin/uptime"),
+                    args: Some(Vec::new()),
+                    digest: None,
+                },
+                CommandRule {
+                    path: String::from("/usr/bin/id"),
+                    args: None,
+                    digest: Some(format!("sha256:{}", "ab".repeat(32))),
+                },
+            ];
+        }
+        let table = vec![
+            ("/usr/bin/systemctl", vec!["restart", "app-web"], Some(0)),
+            ("/usr/bin/systemctl", vec!["edit", "app-web"], None),
+            ("/usr/bin/systemctl", vec!["restart", "app-web", "db"], None),
+            ("/usr/bin/journalctl", vec!["-u", "web-12"], Some(1)),
+            ("/usr/bin/journalctl", vec!["-u", "web-12x"], None),
+            ("/usr/bin/journalctl", vec!["-u", "db-1"], None),
+            ("/usr/bin/uptime", vec![], Some(2)),
+            ("/usr/bin/uptime", vec!["-p"], None),
+            ("/usr/bin/id", vec!["-u", "root"], Some(3)),
+            ("/usr/bin/whoami", vec![], None),
+        ];
+        for (command, args, expected) in table {
+            let mut request = test_request("alice", &["wheel"]);
+            request.command = command.to_owned();
+            request.args = args.iter().map(|&arg| arg.to_owned()).collect();
+            let decision = evaluate(&conf, &request);
+            let verdict_ok = match expected {
+                Some(_) => decision.is_allowed(),
+                None => {
+                    decision.verdict
+                        == Verdict::Deny(RudoError::CommandNotAllowed(super::command_line(
+                            &request,
+                        )))
+                }
+            };
+            let digest_ok = decision.digest.is_some() == (expected == Some(3));
+            if !verdict_ok || !digest_ok || decision.command_rule != expected {
+                return Err(From::from(format!(
+                    "Test failed: {} {:?} got {:?}",
+                    command, args, decision.verdict
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_aliases() -> Result<(), Box<dyn Error>> {
+        let mut conf = test_conf();
+        conf.user.push(UserConf {
+            username: String::from("OPERATORS"),
+            group: String::from("wheel"),
+            commands: vec![CommandRule {
+                path: String::from("SERVICES"),
+                args: None,
+                digest: None,
+            }],
+            runas: vec![String::from("DAEMONS")],
+            hosts: vec![String::from("WEB_HOSTS")],
+            ..UserConf::default()
+        });
+        conf.aliases.users.insert(
+            String::from("OPERATORS"),
+            vec![String::from("bob"), String::from("carol")],
+        );
+        conf.aliases.commands.insert(
+            String::from("SERVICES"),
+            vec![CommandRule {
+                path: String::from("/usr/bin/systemctl"),
+                args: None,
+                digest: None,
+            }],
+        );
+        conf.aliases
+            .runas
+            .insert(String::from("DAEMONS"), vec![String::from("www-data")]);
+        conf.aliases
+            .hosts
+            .insert(String::from("WEB_HOSTS"), vec![String::from("web1")]);
+
+        let mut request = test_request("carol", &["wheel"]);
+        request.host.name = String::from("web1");
+        request.target = String::from("www-data");
+        request.command = String::from("/usr/bin/systemctl");
+        let allowed = evaluate(&conf, &request);
+        request.target = String::from("root");
+        let wrong_target = evaluate(&conf, &request).verdict;
+        request.host.name = String::from("db1");
+        let wrong_host = evaluate(&conf, &request).verdict;
+        conf.aliases.hosts.clear();
+        let undefined = evaluate(&conf, &request).verdict;
+        if allowed.is_allowed()
+            && allowed.rule == Some(2)
+            && allowed.command_rule == Some(0)
+            && wrong_target == Verdict::Deny(RudoError::TargetNotAllowed(String::from("root")))
+            && wrong_host == Verdict::Deny(RudoError::HostNotAllowed(String::from("db1")))
+            && matches!(undefined, Verdict::Deny(RudoError::ConfigParse(_)))
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to evaluate the aliases of a rule"))
+        }
+    }
+
+    #[test]
+    fn test_evaluate_hosts() -> Result<(), Box<dyn Error>> {
+        let mut conf = test_conf();
+        if let Some(alice) = conf.user.get_mut(1) {
+            alice.hosts = vec![String::from("web*"), String::from("10.20.0.0/16")];
+            alice.commands = vec![CommandRule {
+                path: String::from("/usr/bin/systemctl"),
+                args: Some(vec![String::from("restart"), String::from("nginx")]),
+                digest: None,
+            }];
+        }
+        let table = vec![
+            ("web1", None, "192.168.1.5", true),
+            ("web2", Some("web2.example.com"), "192.168.1.6", true),
+            ("db1", Some("db1.example.com"), "10.20.3.4", true),
+            ("db1", Some("db1.example.com"), "10.30.3.4", false),
+            ("db2", None, "fd00::1", false),
+        ];
+        for (name, fqdn, address, allowed) in table {
+            let mut request = test_request("alice", &["wheel"]);
+            request.command = String::from("/usr/bin/systemctl");
+            request.args = vec![String::from("restart"), String::from("nginx")];
+            request.host = Host {
+                name: name.to_owned(),
+                fqdn: fqdn.map(str::to_owned),
+                addresses: vec![address.parse()?],
+            };
+            let decision = evaluate(&conf, &request);
+            let expected = if allowed {
+                Verdict::Allow
+            } else {
+                Verdict::Deny(RudoError::HostNotAllowed(request.host.to_string()))
+            };
+            if decision.verdict != expected {
+                return Err(From::from(format!(
+                    "Test failed: {} got {:?}",
+                    name, decision.verdict
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_allowed_times() -> Result<(), Box<dyn Error>> {
+        let mut conf = test_conf();
+        if let Some(alice) = conf.user.get_mut(1) {
+            alice.allowed_times = Some(serde_yaml::from_str(
+                "timezone: \"+02:00\"\nwindows:\n  - days: mon-fri\n    hours: 09:00-17:00\n",
+            )?);
+        }
+        let mut request = test_request("alice", &["wheel"]);
+        // Monday the 1 of July 2024 at 10:00 and 18:00 at +02:00
+        request.time = UNIX_EPOCH + Duration::from_secs(1_719_820_800);
+        let during = evaluate(&conf, &request);
+        request.time = UNIX_EPOCH + Duration::from_secs(1_719_849_600);
+        let after = evaluate(&conf, &request);
+        if during.is_allowed()
+            && after.verdict
+                == Verdict::Deny(RudoError::OutsideAllowedTimes(String::from(
+                    "mon-fri 09:00-17:00 in +02:00, it's mon 18:00 there",
+                )))
+        {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "Test failed to enforce the allowed times: {}",
+                after.reason
+            )))
+        }
+    }
+
+    #[test]
+    fn test_evaluate_expired() -> Result<(), Box<dyn Error>> {
+        let mut conf = test_conf();
+        if let Some(alice) = conf.user.get_mut(1) {
+            alice.not_after = Some(serde_yaml::from_str("2024-07-01T00:00:00Z")?);
+        }
+        let mut request = test_request("alice", &["wheel"]);
+        request.time = UNIX_EPOCH + Duration::from_secs(1_719_792_000);
+        let last_second = evaluate(&conf, &request);
+        request.time += Duration::from_secs(1);
+        let expired = evaluate(&conf, &request);
+        if last_second.is_allowed()
+            && last_second.inactive.is_empty()
+            && expired.rule.is_none()
+            && expired.inactive == vec![(1, Validity::Expired)]
+            && expired.verdict == Verdict::Deny(RudoError::NotAuthorized(String::from("alice")))
+            && expired
+                .reason
+                .contains("the default rule, as the rule 1 expired")
+        {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "Test failed to ignore the expired rule: {}",
+                expired.reason
+            )))
+        }
+    }
+}
@@ -0,0 +1,462 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::timezone::{self, TimeZone, SECONDS_PER_DAY};
+
+/// The names of the days of the week, from Sunday
+static DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+/// The number of minutes in a day
+const MINUTES_PER_DAY: i64 = 1440;
+
+/// `Timestamp` is an instant written as in RFC 3339, like `2026-10-19T18:00:00Z` or `2026-10-19T14:00-04:00`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Timestamp {
+    /// The seconds since the epoch
+    seconds: i64,
+    /// The instant as written in the configuration
+    text: String,
+}
+
+/// `AllowedTimes` are the windows of the week when a rule allow commands, in the local time of a timezone
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AllowedTimes {
+    /// The timezone of the windows: `local`, `UTC`, a fixed offset like `+05:30`, or a name like `America/Toronto`
+    pub timezone: TimeZone,
+    /// The windows, a time in any of them is allowed
+    pub windows: Vec<TimeWindow>,
+}
+
+/// `TimeWindow` is the hours of some days of the week
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimeWindow {
+    /// The days of the week, like `mon-fri` or `sat,sun`
+    pub days: Days,
+    /// The hours of the days, like `09:00-17:00`
+    pub hours: Hours,
+}
+
+/// `Days` are days of the week, as a list of days and ranges of days
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Days {
+    /// The days of the week that are included, from Sunday
+    included: [bool; 7],
+    /// The days as written in the configuration
+    text: String,
+}
+
+/// `Hours` are the hours of a day, from a time to another, in minutes from midnight.
+/// Hours that end before they begin continue the next day, like `22:00-06:00`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hours {
+    /// The beginning of the hours, included
+    start: i64,
+    /// The end of the hours, excluded
+    end: i64,
+}
+
+impl AllowedTimes {
+    /// Function to know if a time, in seconds since the epoch, is in a window
+    #[must_use]
+    pub fn allows(&self, time: i64) -> bool {
+        let (day, minute) = self.local_day_minute(time);
+        let day_of_week = timezone::weekday(day);
+        self.windows
+            .iter()
+            .any(|window| window.contains(day_of_week, minute))
+    }
+    /// Function to write a time, in seconds since the epoch, as the day of the week and the hour in the timezone
+    #[must_use]
+    pub fn local_time(&self, time: i64) -> String {
+        let (day, minute) = self.local_day_minute(time);
+        let name = usize::try_from(timezone::weekday(day))
+            .ok()
+            .and_then(|index| DAY_NAMES.get(index))
+            .unwrap_or(&"");
+        format!("{} {}", name, format_minute(minute))
+    }
+    /// Give the day since the epoch and the minute of the day of a time in the timezone
+    fn local_day_minute(&self, time: i64) -> (i64, i64) {
+        let local = self.timezone.local_time(time);
+        (
+            local.div_euclid(SECONDS_PER_DAY),
+            local.rem_euclid(SECONDS_PER_DAY).div_euclid(60),
+        )
+    }
+}
+
+impl fmt::Display for AllowedTimes {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let windows: Vec<String> = self.windows.iter().map(ToString::to_string).collect();
+        write!(formatter, "{} in {}", windows.join(", "), self.timezone)
+    }
+}
+
+impl TimeWindow {
+    /// Function to know if a minute of a day of the week is in the window
+    #[must_use]
+    pub fn contains(&self, day_of_week: i64, minute: i64) -> bool {
+        if self.hours.start < self.hours.end {
+            self.days.contains(day_of_week) && self.hours.start <= minute && minute < self.hours.end
+        } else {
+            // The end of the hours is in the next day
+            (self.days.contains(day_of_week) && self.hours.start <= minute)
+                || (self
+                    .days
+                    .contains(day_of_week.saturating_add(6).rem_euclid(7))
+                    && minute < self.hours.end)
+        }
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} {}", self.days, self.hours)
+    }
+}
+
+impl Days {
+    /// Function to know if a day of the week, from 0 for Sunday, is included
+    #[must_use]
+    pub fn contains(&self, day_of_week: i64) -> bool {
+        usize::try_from(day_of_week)
+            .ok()
+            .and_then(|index| self.included.get(index))
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+impl TryFrom<String> for Days {
+    type Error = Box<dyn Error>;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let mut included = [false; 7];
+        for item in text.split(',') {
+            let mut range = item.splitn(2, '-').map(day_index);
+            let first = range.next().unwrap_or(None);
+            let last = range.next().unwrap_or(first);
+            let (mut day, last_day) = match (first, last) {
+                (Some(first_day), Some(last_day)) => (first_day, last_day),
+                _ => {
+                    return Err(From::from(format!(
+                        "invalid days {}, they must be like mon-fri or sat,sun",
+                        text
+                    )))
+                }
+            };
+            // A range can wrap around the end of the week, like fri-mon
+            loop {
+                if let Some(included_day) = included.get_mut(day) {
+                    *included_day = true;
+                }
+                if day == last_day {
+                    break;
+                }
+                day = day.saturating_add(1).rem_euclid(7);
+            }
+        }
+        Ok(Self { included, text })
+    }
+}
+
+impl From<Days> for String {
+    fn from(days: Days) -> Self {
+        days.text
+    }
+}
+
+impl fmt::Display for Days {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.text)
+    }
+}
+
+impl TryFrom<String> for Hours {
+    type Error = Box<dyn Error>;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let mut parts = text.splitn(2, '-').map(|part| parse_minute(part.trim()));
+        match (parts.next().flatten(), parts.next().flatten()) {
+            (Some(start), Some(end)) if start != end && start < MINUTES_PER_DAY => {
+                Ok(Self { start, end })
+            }
+            _ => Err(From::from(format!(
+                "invalid hours {}, they must be like 09:00-17:00, from 00:00 to 24:00",
+                text
+            ))),
+        }
+    }
+}
+
+impl From<Hours> for String {
+    fn from(hours: Hours) -> Self {
+        hours.to_string()
+    }
+}
+
+impl fmt::Display for Hours {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}-{}",
+            format_minute(self.start),
+            format_minute(self.end)
+        )
+    }
+}
+
+impl Timestamp {
+    /// Function to give the instant in seconds since the epoch
+    #[must_use]
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+}
+
+impl TryFrom<String> for Timestamp {
+    type Error = Box<dyn Error>;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let seconds = parse_timestamp(&text).ok_or_else(|| {
+            format!(
+                "invalid instant {}, it must be like 2026-10-19T18:00:00Z or 2026-10-19T14:00:00-04:00",
+                text
+            )
+        })?;
+        Ok(Self { seconds, text })
+    }
+}
+
+impl From<Timestamp> for String {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.text
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.text)
+    }
+}
+
+/// Function to give a time in seconds since the epoch, none if it's before it
+#[must_use]
+pub fn unix_time(time: SystemTime) -> Option<i64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|since_epoch| i64::try_from(since_epoch.as_secs()).ok())
+}
+
+/// Function to parse an instant of RFC 3339 in seconds since the epoch, the seconds and their fractions are optional
+fn parse_timestamp(text: &str) -> Option<i64> {
+    let mut parts = text.splitn(2, |character| character == 'T' || character == 't');
+    let date = parts.next()?;
+    let time_and_offset = parts.next()?;
+    // The offset is Z for UTC, or the one of the local time like -04:00
+    let (time, offset) = if time_and_offset.ends_with('Z') || time_and_offset.ends_with('z') {
+        (
+            time_and_offset.get(..time_and_offset.len().saturating_sub(1))?,
+            0,
+        )
+    } else {
+        let sign = time_and_offset.rfind(|character| character == '+' || character == '-')?;
+        (
+            time_and_offset.get(..sign)?,
+            timezone::parse_offset(time_and_offset.get(sign..)?).ok()?,
+        )
+    };
+
+    let mut date_parts = date.splitn(3, '-').map(str::parse::<i64>);
+    let year = date_parts.next()?.ok()?;
+    let month = date_parts
+        .next()?
+        .ok()
+        .filter(|month| (1..=12).contains(month))?;
+    let day = date_parts.next()?.ok()?;
+    let first_of_month = timezone::days_from_civil(year, month, 1);
+    let days_in_month =
+        timezone::days_from_civil(year, month.saturating_add(1), 1).saturating_sub(first_of_month);
+    if day < 1 || day > days_in_month || date.len() != 10 {
+        return None;
+    }
+
+    // The fraction of the seconds is ignored
+    let whole_time = time.split('.').next()?;
+    let mut time_parts = whole_time.splitn(3, ':').map(str::parse::<i64>);
+    let hours = time_parts
+        .next()?
+        .ok()
+        .filter(|hours| (0..=23).contains(hours))?;
+    let minutes = time_parts
+        .next()?
+        .ok()
+        .filter(|minutes| (0..=59).contains(minutes))?;
+    let seconds = match time_parts.next() {
+        Some(parsed) => parsed.ok().filter(|seconds| (0..=60).contains(seconds))?,
+        None => 0,
+    };
+    Some(
+        first_of_month
+            .saturating_add(day.saturating_sub(1))
+            .saturating_mul(SECONDS_PER_DAY)
+            .saturating_add(hours.saturating_mul(3600))
+            .saturating_add(minutes.saturating_mul(60))
+            .saturating_add(seconds)
+            .saturating_sub(offset),
+    )
+}
+
+/// Function to give the index of a day of the week from its name, from 0 for Sunday
+fn day_index(name: &str) -> Option<usize> {
+    let lowercase = name.trim().to_lowercase();
+    DAY_NAMES.iter().position(|&day| day == lowercase)
+}
+
+/// Function to parse a time like `09:30` in minutes from midnight, `24:00` is the end of the day
+fn parse_minute(text: &str) -> Option<i64> {
+    let mut parts = text.splitn(2, ':');
+    let hours = parts.next()?.parse::<i64>().ok()?;
+    let minutes = parts.next()?.parse::<i64>().ok()?;
+    let minute = hours.saturating_mul(60).saturating_add(minutes);
+    if (0..=23).contains(&hours) && (0..=59).contains(&minutes) || minute == MINUTES_PER_DAY {
+        Some(minute)
+    } else {
+        None
+    }
+}
+
+/// Function to write minutes from midnight like `09:30`
+fn format_minute(minute: i64) -> String {
+    format!("{:02}:{:02}", minute.div_euclid(60), minute.rem_euclid(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryFrom, error::Error};
+
+    use super::{AllowedTimes, Days, Hours, Timestamp};
+
+    /// The 1 of July 2024, a Monday, at 00:00 UTC
+    const MONDAY: i64 = 1_719_792_000;
+
+    /// Create the allowed times of the tests, the business hours and Saturday night at -05:00
+    fn business_hours() -> Result<AllowedTimes, Box<dyn Error>> {
+        let data = "timezone: \"-05:00\"\nwindows:\n  - days: mon-fri\n    hours: 09:00-17:00\n  - days: sat\n    hours: 22:00-02:00\n";
+        Ok(serde_yaml::from_str(data)?)
+    }
+
+    #[test]
+    fn test_allows() -> Result<(), Box<dyn Error>> {
+        let times = business_hours()?;
+        let hour = 3600;
+        let table = vec![
+            // Monday 09:00 in Toronto is 14:00 UTC
+            (MONDAY + 14 * hour, true),
+            (MONDAY + 14 * hour - 60, false),
+            (MONDAY + 22 * hour - 60, true),
+            (MONDAY + 22 * hour, false),
+            // Saturday 23:00 and Sunday 01:00 are in the window of Saturday night
+            (MONDAY + (5 * 24 + 28) * hour, true),
+            (MONDAY + (6 * 24 + 6) * hour, true),
+            (MONDAY + (6 * 24 + 7) * hour, false),
+        ];
+        for (time, expected) in table {
+            if times.allows(time) != expected {
+                return Err(From::from(format!(
+                    "Test failed: {} should be {}",
+                    times.local_time(time),
+                    expected
+                )));
+            }
+        }
+        if times.to_string() == "mon-fri 09:00-17:00, sat 22:00-02:00 in -05:00"
+            && times.local_time(MONDAY) == "sun 19:00"
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to describe the allowed times"))
+        }
+    }
+
+    #[test]
+    fn test_days() -> Result<(), Box<dyn Error>> {
+        let weekend = Days::try_from(String::from("fri-mon"))?;
+        let list = Days::try_from(String::from("Mon,wed"))?;
+        if weekend.contains(5)
+            && weekend.contains(0)
+            && weekend.contains(1)
+            && !weekend.contains(3)
+            && list.contains(1)
+            && list.contains(3)
+            && !list.contains(2)
+            && Days::try_from(String::from("monday")).is_err()
+            && Days::try_from(String::from("mon-")).is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to parse the days"))
+        }
+    }
+
+    #[test]
+    fn test_hours() -> Result<(), Box<dyn Error>> {
+        let day = Hours::try_from(String::from("00:00-24:00"))?;
+        if day.to_string() == "00:00-24:00"
+            && Hours::try_from(String::from("9:30-17:00")).is_ok()
+            && Hours::try_from(String::from("09:00-09:00")).is_err()
+            && Hours::try_from(String::from("24:00-06:00")).is_err()
+            && Hours::try_from(String::from("09:60-10:00")).is_err()
+            && Hours::try_from(String::from("09:00")).is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to parse the hours"))
+        }
+    }
+
+    #[test]
+    fn test_timestamp() -> Result<(), Box<dyn Error>> {
+        let utc = Timestamp::try_from(String::from("2024-07-01T00:00:00Z"))?;
+        let toronto = Timestamp::try_from(String::from("2024-06-30T20:00-04:00"))?;
+        let leap_day = Timestamp::try_from(String::from("2024-02-29T12:30:15.250+01:00"))?;
+        if utc.seconds() == MONDAY
+            && toronto.seconds() == MONDAY
+            && leap_day.seconds() == 1_709_206_215
+            && utc.to_string() == "2024-07-01T00:00:00Z"
+            && Timestamp::try_from(String::from("2023-02-29T00:00:00Z")).is_err()
+            && Timestamp::try_from(String::from("2024-07-01")).is_err()
+            && Timestamp::try_from(String::from("2024-07-01T25:00:00Z")).is_err()
+            && Timestamp::try_from(String::from("2024-7-1T10:00:00Z")).is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to parse the instants"))
+        }
+    }
+}
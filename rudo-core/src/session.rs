@@ -0,0 +1,463 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{error::Error, path::Path, time::Duration};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dir::{Dir, UntrustedFile},
+    error::RudoError,
+    journal,
+    signature::{self, TokenKey},
+    utils, DEFAULT_SESSION_TIMEOUT, ROOT_UID, SESSION_PATH,
+};
+
+/// Permissions of the directories that contain the token, only root can access them
+static DIR_MODE: u32 = 0o700;
+/// Permissions of the token file
+static TOKEN_MODE: u32 = 0o600;
+
+/// Create a structure to contain the UUID of the terminal, and the timestamp to determine
+/// if the session is valid for later use.
+/// The timestamps come from the boot time clock, so they are only meaningful for the boot that created them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Token {
+    /// Name of the TTY
+    tty_name: String,
+    /// UUID of the TTY
+    tty_uuid: String,
+    /// The account whose password was given, a token of an older version has none
+    #[serde(default)]
+    account: String,
+    /// Identifier of the boot where the token was created
+    boot_id: String,
+    /// The timestamp determine at the creation of the token
+    timestamp: Duration,
+    /// The timestamp plus the DEFAULT_SESSION_TIMEOUT to determine the maximum validity of the session
+    final_timestamp: Duration,
+}
+
+impl Token {
+    /// Create the token and all it's parameter
+    ///
+    /// # Errors
+    /// Return an error if the boot identifier or the boot time clock couldn't be read
+    pub fn new(tty_name: &str, tty_uuid: &str, account: &str) -> Result<Self, Box<dyn Error>> {
+        debug!("Extract the identifier of the actual boot");
+        let boot_id = utils::read_boot_id()?;
+        debug!("Create the timestamp of the token");
+        let timestamp = utils::boottime()?;
+        // Create the timestamp where the session become invalid
+        debug!("Create the final timestamp to determine the maximum validity of the session");
+        let duration = Duration::from_secs(DEFAULT_SESSION_TIMEOUT);
+        let final_timestamp = match timestamp.checked_add(duration) {
+            Some(time) => time,
+            None => return Err(From::from("Couldn't create final timestamp")),
+        };
+        Ok(Self {
+            tty_name: String::from(tty_name),
+            tty_uuid: String::from(tty_uuid),
+            account: String::from(account),
+            boot_id,
+            timestamp,
+            final_timestamp,
+        })
+    }
+    /// Create the file that will contain the token, or replace the precedent one
+    ///
+    /// # Errors
+    /// Return an error if the token couldn't be signed with `key` or written
+    pub fn create_token_file(&self, dir: &Dir, key: &TokenKey) -> Result<(), Box<dyn Error>> {
+        // The name of the TTY let the user have multiple session, on multiple terminal
+        let token_name = token_name(&self.tty_name);
+        debug!("Token will be written to {}", token_name);
+
+        // Put the token data in a string of YAML
+        debug!("Put Token in a string");
+        let token_data = serde_yaml::to_string(&self)?;
+
+        // Sign the token, so any modification of the file can be detected
+        debug!("Sign the token with the key of Rudo");
+        let signed_token = SignedToken {
+            signature: signature::sign(key, &token_data)?,
+            token: token_data,
+        };
+        let token_file = serde_yaml::to_string(&signed_token)?;
+
+        // Write the token file, replacing atomically the precedent one
+        dir.write_file(&token_name, TOKEN_MODE, token_file.as_bytes())?;
+        Ok(())
+    }
+    /// Verify that the token is valid to decide if we must reuse the session or not
+    ///
+    /// # Errors
+    /// Return an error if the token has expired, was created during another boot, or belongs to another terminal or account
+    pub fn verify_token(
+        &self,
+        tty_name: &str,
+        tty_uuid: &str,
+        account: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.verify_validity()?;
+        if self.tty_name != tty_name || self.tty_uuid != tty_uuid {
+            debug!("Not the same session");
+            Err(Box::new(RudoError::TokenInvalid(String::from(
+                "Not the same session",
+            ))))
+        } else if self.account != account {
+            // The password of another account was given, like the one of another target user
+            debug!("Session was authenticated as {}", self.account);
+            Err(Box::new(RudoError::TokenInvalid(String::from(
+                "Session was authenticated with another account",
+            ))))
+        } else {
+            debug!("Session is valid, will reuse it");
+            Ok(())
+        }
+    }
+    /// Verify that the token was created during this boot, and that it has not expired
+    fn verify_validity(&self) -> Result<(), Box<dyn Error>> {
+        let boot_id = utils::read_boot_id()?;
+        let clock = utils::boottime()?;
+        if self.boot_id != boot_id {
+            debug!("Token was created during another boot");
+            Err(Box::new(RudoError::TokenInvalid(String::from(
+                "Token was created during another boot",
+            ))))
+        } else if self.timestamp > clock {
+            warn!("Token has a timestamp in the future");
+            Err(Box::new(RudoError::TokenInvalid(String::from(
+                "Token has a timestamp in the future",
+            ))))
+        } else if self.final_timestamp <= clock {
+            debug!("Session has expired");
+            Err(Box::new(RudoError::TokenInvalid(String::from(
+                "Session has expired",
+            ))))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Structure of the token file, the token in YAML and its signature
+#[derive(Serialize, Deserialize)]
+struct SignedToken {
+    /// The token serialized in YAML, exactly as it was signed
+    token: String,
+    /// The HMAC of the token in hexadecimal
+    signature: String,
+}
+
+/// Create the name of the token file from the name of the TTY, like `pts_0` for `/dev/pts/0`
+#[must_use]
+pub fn token_name(tty_name: &str) -> String {
+    let name = tty_name.trim_start_matches("/dev/");
+    name.replace('/', "_")
+}
+
+/// Open the directory containing the directories of every user, creating it if necessary.
+/// It must belong to root with mode 700, or Rudo refuses to use it
+fn open_dir_run() -> Result<Dir, Box<dyn Error>> {
+    Dir::open_or_create_path(Path::new(SESSION_PATH), ROOT_UID, DIR_MODE)
+}
+
+/// Open the directory containing the token file of the user, creating every part of it if necessary.
+/// Every directory must belong to root with mode 700, or Rudo refuses to use it
+///
+/// # Errors
+/// Return an error if a directory couldn't be opened or created
+pub fn create_dir_run(username: &str) -> Result<Dir, Box<dyn Error>> {
+    let run_dir = open_dir_run()?;
+    debug!(
+        "Opening or creating {}{} with mode 700",
+        SESSION_PATH, username
+    );
+    run_dir.open_or_create_dir(username, DIR_MODE)
+}
+
+/// Remove the token of the directory that are expired, from another boot or that can't be trusted,
+/// and the temporary files of the writes that were interrupted. A token that couldn't be verified,
+/// or an entry that can't be removed, is skipped.
+/// Return the number of files removed
+///
+/// # Errors
+/// Return an error if the directory couldn't be read
+pub fn prune_tokens(dir: &Dir, key: &TokenKey) -> Result<usize, Box<dyn Error>> {
+    let mut count: usize = 0;
+    for token_name in dir.entries()? {
+        let stale = if token_name.starts_with('.') {
+            // The temporary file of a token is only stale when the process writing it is gone
+            is_abandoned(&token_name)
+        } else {
+            is_invalid(dir, &token_name, key)
+        };
+        if !stale {
+            continue;
+        }
+        debug!("Removing the stale file {}", token_name);
+        match dir.remove_file(&token_name) {
+            Ok(()) => count = count.saturating_add(1),
+            Err(err) => warn!("Couldn't remove {}, it's skipped: {}", token_name, err),
+        }
+    }
+    Ok(count)
+}
+
+/// Function to find if a token was judged invalid, like a bad signature or an expired session.
+/// Any other error, like a failure to read the file, doesn't say anything about the token, so it's kept
+fn is_invalid(dir: &Dir, token_name: &str, key: &TokenKey) -> bool {
+    let result = match read_token_file(dir, token_name, key) {
+        Ok(Some(token)) => token.verify_validity(),
+        Ok(None) => return false,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => false,
+        Err(err) => {
+            if let Some(RudoError::TokenInvalid(_)) = err.downcast_ref::<RudoError>() {
+                return true;
+            }
+            warn!(
+                "Couldn't verify the token {}, it's skipped: {}",
+                token_name, err
+            );
+            false
+        }
+    }
+}
+
+/// Function to find if a temporary file, named `.name.pid`, was left by a process that is gone
+fn is_abandoned(file_name: &str) -> bool {
+    let pid = file_name
+        .rfind('.')
+        .filter(|&index| index > 0)
+        .and_then(|index| file_name.get(index.saturating_add(1)..))
+        .and_then(|digits| digits.parse::<i32>().ok());
+    match pid {
+        Some(writer) if writer > 0 => !utils::process_exists(writer),
+        _ => false,
+    }
+}
+
+/// Remove the stale token of every user. A directory that can't be cleaned is skipped.
+/// Return the number of files removed
+///
+/// # Errors
+/// Return an error if the directory of the token couldn't be read, or if the key couldn't be loaded
+pub fn collect_garbage() -> Result<usize, Box<dyn Error>> {
+    let run_dir = open_dir_run()?;
+    let key = TokenKey::load()?;
+    let mut count: usize = 0;
+    for username in run_dir.entries()? {
+        debug!("Removing the stale token of {}", username);
+        let result = run_dir
+            .open_dir(&username, DIR_MODE)
+            .and_then(|user_dir| prune_tokens(&user_dir, &key));
+        match result {
+            Ok(removed) => count = count.saturating_add(removed),
+            Err(err) => warn!(
+                "Couldn't clean {}{}, it's skipped: {}",
+                SESSION_PATH, username, err
+            ),
+        }
+    }
+    info!("{} stale token removed from {}", count, SESSION_PATH);
+    Ok(count)
+}
+
+/// Function to extract the token from its file with `serde_yaml`.
+/// The file must belong to root, have mode 600, not be a symlink, and have a valid signature with `key`.
+/// Anything else is reported as a security event. Return `None` if the token doesn't exist
+///
+/// # Errors
+/// Return an error if the token couldn't be read, or `TokenInvalid` if it can't be trusted
+pub fn read_token_file(
+    dir: &Dir,
+    token_name: &str,
+    key: &TokenKey,
+) -> Result<Option<Token>, Box<dyn Error>> {
+    // Open the file and extract its contents in a buffer
+    debug!(
+        "Open the file {} and put it's content in a buffer",
+        token_name
+    );
+    let buffer = match dir.read_file(token_name, TOKEN_MODE) {
+        Ok(Some(buffer)) => buffer,
+        Ok(None) => return Ok(None),
+        Err(err) if err.is::<UntrustedFile>() => {
+            return Err(Box::new(RudoError::TokenInvalid(err.to_string())))
+        }
+        Err(err) => return Err(err),
+    };
+
+    // Verify the signature of the token before trusting its content
+    debug!("Verify the signature of the token");
+    let token_data = match verify_signed_token(key, &buffer) {
+        Ok(token_data) => token_data,
+        Err(err) => {
+            let message = format!("Token {} was tampered: {}", token_name, err);
+            journal::security_event(&message);
+            return Err(Box::new(RudoError::TokenInvalid(message)));
+        }
+    };
+
+    // Transform the buffer to the token structure
+    debug!("Transform the buffer to the token structure");
+    match serde_yaml::from_str(&token_data) {
+        Ok(token) => Ok(Some(token)),
+        Err(err) => Err(Box::new(RudoError::TokenInvalid(format!(
+            "Token {} is invalid: {}",
+            token_name, err
+        )))),
+    }
+}
+
+/// Function to verify the signature of the content of a token file and return the signed token
+fn verify_signed_token(key: &TokenKey, buffer: &[u8]) -> Result<String, Box<dyn Error>> {
+    let signed_token: SignedToken = serde_yaml::from_slice(buffer)?;
+    signature::verify(key, &signed_token.token, &signed_token.signature)?;
+    Ok(signed_token.token)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, DirBuilder},
+        os::unix::fs::DirBuilderExt,
+        process,
+    };
+
+    use super::{
+        prune_tokens, signature, token_name, utils, verify_signed_token, Dir, Duration, Error,
+        SignedToken, Token, TokenKey, DEFAULT_SESSION_TIMEOUT,
+    };
+
+    #[test]
+    fn test_timestamp() -> Result<(), Box<dyn Error>> {
+        let token = Token::new("name", "1234", "root")?;
+        let duration = Duration::from_secs(DEFAULT_SESSION_TIMEOUT);
+        if token.final_timestamp.checked_sub(duration) == Some(token.timestamp) {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: timestamp creation got wrong"))
+        }
+    }
+
+    #[test]
+    fn test_verify_token() -> Result<(), Box<dyn Error>> {
+        let token = Token::new("name", "1234", "root")?;
+        token.verify_token("name", "1234", "root")
+    }
+
+    #[test]
+    fn test_verify_token_other_account() -> Result<(), Box<dyn Error>> {
+        let token = Token::new("name", "1234", "root")?;
+        if token.verify_token("name", "1234", "alice").is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: token of another account was accepted",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_token_other_boot() -> Result<(), Box<dyn Error>> {
+        let mut token = Token::new("name", "1234", "root")?;
+        token.boot_id = String::from("00000000-0000-0000-0000-000000000000");
+        if token.verify_token("name", "1234", "root").is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: token from another boot was accepted",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_token_future() -> Result<(), Box<dyn Error>> {
+        let mut token = Token::new("name", "1234", "root")?;
+        token.timestamp += Duration::from_secs(DEFAULT_SESSION_TIMEOUT);
+        if token.verify_token("name", "1234", "root").is_err() {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: token from the future was accepted",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_signed_token() -> Result<(), Box<dyn Error>> {
+        let key = TokenKey::new(vec![7; 32])?;
+        let token_data = serde_yaml::to_string(&Token::new("name", "1234", "root")?)?;
+        let signed_token = SignedToken {
+            signature: signature::sign(&key, &token_data)?,
+            token: token_data.clone(),
+        };
+        let buffer = serde_yaml::to_string(&signed_token)?;
+        let tampered = buffer.replace("1234", "5678");
+        if verify_signed_token(&key, buffer.as_bytes())? == token_data
+            && verify_signed_token(&key, tampered.as_bytes()).is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to verify the signature of the token",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_token_name() -> Result<(), Box<dyn Error>> {
+        if token_name("/dev/pts/0") == "pts_0" && token_name("/dev/tty1") == "tty1" {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to create the name of the token"))
+        }
+    }
+
+    #[test]
+    fn test_prune_tokens() -> Result<(), Box<dyn Error>> {
+        let path = utils::test_dir("prune_tokens")?;
+        let dir = Dir::open(&path, users::get_effective_uid())?;
+        let key = TokenKey::new(vec![7; 32])?;
+        Token::new("/dev/pts/4", "1234", "root")?.create_token_file(&dir, &key)?;
+        let mut expired = Token::new("/dev/pts/5", "1234", "root")?;
+        expired.final_timestamp = expired.timestamp;
+        expired.create_token_file(&dir, &key)?;
+        let in_progress = format!(".pts_1.{}", process::id());
+        dir.write_file("pts_0", 0o600, b"garbage")?;
+        dir.write_file(&in_progress, 0o600, b"in progress")?;
+        // A pid is never higher than 2^22 on Linux, so its process is gone
+        dir.write_file(".pts_2.999999999", 0o600, b"interrupted")?;
+        DirBuilder::new().mode(0o700).create(path.join("pts_3"))?;
+        let count = prune_tokens(&dir, &key)?;
+        let mut entries = dir.entries()?;
+        entries.sort();
+        fs::remove_dir_all(&path)?;
+        if count == 3 && entries == vec![in_progress, String::from("pts_3"), String::from("pts_4")]
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to remove the stale token"))
+        }
+    }
+}
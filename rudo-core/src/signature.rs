@@ -0,0 +1,162 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    fmt,
+    fs::{DirBuilder, File, OpenOptions},
+    io::{Read, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::Path,
+};
+
+use hmac::{Hmac, Mac};
+use log::{debug, info};
+use sha2::Sha256;
+
+use crate::{utils, ROOT_UID, STATE_PATH, TOKEN_KEY_PATH};
+
+/// HMAC with SHA-256 is use to sign the token
+type HmacSha256 = Hmac<Sha256>;
+
+/// The length in bytes of the key that sign the token
+static KEY_LENGTH: usize = 32;
+/// The permissions of the key, only root can read it
+static KEY_MODE: u32 = 0o600;
+
+/// `TokenKey` is the key that sign the token, given to every function that sign or verify one
+pub struct TokenKey {
+    /// The bytes of the key, always `KEY_LENGTH` long
+    bytes: Vec<u8>,
+}
+
+impl TokenKey {
+    /// Create the key from its bytes, like a key that was read elsewhere than `TOKEN_KEY_PATH`
+    ///
+    /// # Errors
+    /// Return an error if the key doesn't have the right length
+    pub fn new(bytes: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() != KEY_LENGTH {
+            return Err(From::from("The token key doesn't have the right length"));
+        }
+        Ok(Self { bytes })
+    }
+    /// Load the key of Rudo from `TOKEN_KEY_PATH`, or create it on the first run
+    ///
+    /// # Errors
+    /// Return an error if the key couldn't be created or read, or if it isn't protected
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let key_path = Path::new(TOKEN_KEY_PATH);
+        if key_path.symlink_metadata().is_err() {
+            info!("{} doesn't exist! Creating it", TOKEN_KEY_PATH);
+            create_key(key_path)?;
+        }
+        debug!("Loading the token key from {}", TOKEN_KEY_PATH);
+        let key = utils::read_protected_file(key_path, ROOT_UID, KEY_MODE)?;
+        Self::new(key).map_err(|err| From::from(format!("{}: {}", TOKEN_KEY_PATH, err)))
+    }
+}
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The key is secret, it must never be written in the logs
+        formatter.write_str("TokenKey")
+    }
+}
+
+/// Function to generate a random key and to write it with permissions that only root can read
+fn create_key(key_path: &Path) -> Result<(), Box<dyn Error>> {
+    debug!("Creating {} with mode 700", STATE_PATH);
+    DirBuilder::new()
+        .mode(0o700)
+        .recursive(true)
+        .create(STATE_PATH)?;
+
+    debug!("Generating a random key");
+    let mut key = vec![0; KEY_LENGTH];
+    File::open("/dev/urandom")?.read_exact(&mut key)?;
+
+    // Refuse to replace a file that someone created in between
+    debug!("Writing the key to {}", TOKEN_KEY_PATH);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(KEY_MODE)
+        .open(key_path)?;
+    file.write_all(&key)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Function to create the HMAC of the data with the key
+fn new_mac(key: &TokenKey, data: &str) -> Result<HmacSha256, Box<dyn Error>> {
+    let mut mac = match HmacSha256::new_from_slice(&key.bytes) {
+        Ok(mac) => mac,
+        Err(_) => return Err(From::from("Invalid length for the token key")),
+    };
+    mac.update(data.as_bytes());
+    Ok(mac)
+}
+
+/// Function to sign the data and return the signature in hexadecimal
+///
+/// # Errors
+/// Return an error if the key can't be used
+pub(crate) fn sign(key: &TokenKey, data: &str) -> Result<String, Box<dyn Error>> {
+    let mac = new_mac(key, data)?;
+    Ok(utils::to_hex(&mac.finalize().into_bytes()))
+}
+
+/// Function to verify that the signature correspond to the data.
+/// The comparison is done in constant time
+///
+/// # Errors
+/// Return an error if the signature is invalid or doesn't match the data
+pub(crate) fn verify(key: &TokenKey, data: &str, signature: &str) -> Result<(), Box<dyn Error>> {
+    let bytes = utils::from_hex(signature)?;
+    let mac = new_mac(key, data)?;
+    if mac.verify_slice(&bytes).is_err() {
+        return Err(From::from("The signature doesn't correspond to the data"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify, Error, TokenKey, KEY_LENGTH};
+
+    #[test]
+    fn test_sign_verify() -> Result<(), Box<dyn Error>> {
+        let key = TokenKey::new(vec![7; KEY_LENGTH])?;
+        let signature = sign(&key, "token")?;
+        verify(&key, "token", &signature)
+    }
+
+    #[test]
+    fn test_verify_tampered() -> Result<(), Box<dyn Error>> {
+        let key = TokenKey::new(vec![7; KEY_LENGTH])?;
+        let signature = sign(&key, "token")?;
+        let other_key = TokenKey::new(vec![8; KEY_LENGTH])?;
+        if verify(&key, "tokem", &signature).is_err()
+            && verify(&other_key, "token", &signature).is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a tampered token was accepted"))
+        }
+    }
+}
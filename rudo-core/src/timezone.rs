@@ -0,0 +1,292 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{convert::TryFrom, error::Error, fmt, fs};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+/// The directory of the timezone database
+static ZONEINFO_PATH: &str = "/usr/share/zoneinfo";
+/// The file of the timezone of the system
+static LOCALTIME_PATH: &str = "/etc/localtime";
+/// The number of seconds in a day
+pub(crate) const SECONDS_PER_DAY: i64 = 86_400;
+/// The number of seconds in an hour
+const SECONDS_PER_HOUR: i64 = 3600;
+
+/// `TimeZone` give the offset of the local time from UTC at any time.
+/// It's `UTC`, `local` for the timezone of the system, a fixed offset like `+05:30`,
+/// or the name of a timezone of the database, like `America/Toronto`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeZone {
+    /// The name of the timezone, as written in the configuration
+    name: String,
+    /// Where the offset of the timezone come from
+    source: Source,
+}
+
+/// `Source` is where the offset of a timezone come from
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    /// The offset never change, in seconds
+    Fixed(i64),
+    /// A file of the database, with its transitions and the rule that follow them
+    File(tz::TimeZone),
+}
+
+impl TimeZone {
+    /// Function to load a timezone by its name, the file of the database is verified for the names that aren't `UTC`
+    /// or an offset
+    ///
+    /// # Errors
+    /// Return an error if the name is invalid, or if the file of the timezone couldn't be read or isn't valid
+    pub fn new(name: &str) -> Result<Self, Box<dyn Error>> {
+        if name == "UTC" {
+            Ok(Self::fixed(name, 0))
+        } else if name.starts_with('+') || name.starts_with('-') {
+            Ok(Self::fixed(name, parse_offset(name)?))
+        } else if name == "local" {
+            Self::from_file(name, LOCALTIME_PATH)
+        } else if is_zone_name(name) {
+            Self::from_file(name, &format!("{}/{}", ZONEINFO_PATH, name))
+        } else {
+            Err(From::from(format!("invalid timezone {}", name)))
+        }
+    }
+    /// Function to create a timezone that always has the same offset, in seconds
+    fn fixed(name: &str, offset: i64) -> Self {
+        Self {
+            name: name.to_owned(),
+            source: Source::Fixed(offset),
+        }
+    }
+    /// Function to create the timezone of a file in the `TZif` format of the database
+    fn from_file(name: &str, path: &str) -> Result<Self, Box<dyn Error>> {
+        let data = fs::read(path).map_err(|err| {
+            format!(
+                "unknown timezone {}, {} couldn't be read: {}",
+                name, path, err
+            )
+        })?;
+        let timezone = tz::TimeZone::from_tz_data(&data).map_err(|err| {
+            format!(
+                "invalid timezone {}, {} isn't a timezone file: {}",
+                name, path, err
+            )
+        })?;
+        Ok(Self {
+            name: name.to_owned(),
+            source: Source::File(timezone),
+        })
+    }
+    /// Function to give the offset from UTC in seconds, at a time in seconds since the epoch
+    #[must_use]
+    pub fn offset_at(&self, time: i64) -> i64 {
+        match self.source {
+            Source::Fixed(offset) => offset,
+            Source::File(ref timezone) => match timezone.find_local_time_type(time) {
+                Ok(local_time_type) => i64::from(local_time_type.ut_offset()),
+                Err(err) => {
+                    error!(
+                        "The offset of the timezone {} couldn't be found, UTC is used: {}",
+                        self.name, err
+                    );
+                    0
+                }
+            },
+        }
+    }
+    /// Function to convert a time in seconds since the epoch to the local time, in seconds since the local epoch
+    #[must_use]
+    pub fn local_time(&self, time: i64) -> i64 {
+        time.saturating_add(self.offset_at(time))
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = Box<dyn Error>;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::new(&name)
+    }
+}
+
+impl From<TimeZone> for String {
+    fn from(timezone: TimeZone) -> Self {
+        timezone.name
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.name)
+    }
+}
+
+/// Function to give the day of the week of a day since the epoch, from 0 for Sunday to 6 for Saturday
+#[must_use]
+pub(crate) fn weekday(day: i64) -> i64 {
+    // The first of January 1970 was a Thursday
+    day.saturating_add(4).rem_euclid(7)
+}
+
+/// Function to know if a name is the one of a file of the database, it must stay in its directory
+fn is_zone_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('/')
+        && name.split('/').all(|part| part != ".." && part != ".")
+        && name.chars().all(|character| {
+            character.is_ascii_alphanumeric() || matches!(character, '/' | '_' | '-' | '+')
+        })
+}
+
+/// Function to parse a fixed offset, like `+05:30` or `-03:00`, in seconds
+pub(crate) fn parse_offset(text: &str) -> Result<i64, Box<dyn Error>> {
+    let invalid = || format!("invalid offset {}, it must be like +05:30 or -03:00", text);
+    let sign = if text.starts_with('-') { -1 } else { 1 };
+    let mut parts = text.get(1..).unwrap_or_default().splitn(2, ':');
+    let hours = parts
+        .next()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .filter(|hours| (0..=23).contains(hours))
+        .ok_or_else(invalid)?;
+    let minutes = parts
+        .next()
+        .and_then(|minutes| minutes.parse::<i64>().ok())
+        .filter(|minutes| (0..=59).contains(minutes))
+        .ok_or_else(invalid)?;
+    Ok(hours
+        .saturating_mul(SECONDS_PER_HOUR)
+        .saturating_add(minutes.saturating_mul(60))
+        .saturating_mul(sign))
+}
+
+/// Function to give the day since the epoch of a date, from the algorithms of Howard Hinnant
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // The years begin in March, so the leap day is the last day of the year
+    let shifted_year = if month <= 2 {
+        year.saturating_sub(1)
+    } else {
+        year
+    };
+    let era = shifted_year.div_euclid(400);
+    let year_of_era = shifted_year.rem_euclid(400);
+    let day_of_year = month
+        .saturating_add(9)
+        .rem_euclid(12)
+        .saturating_mul(153)
+        .saturating_add(2)
+        .div_euclid(5)
+        .saturating_add(day)
+        .saturating_sub(1);
+    let day_of_era = year_of_era
+        .saturating_mul(365)
+        .saturating_add(year_of_era.div_euclid(4))
+        .saturating_sub(year_of_era.div_euclid(100))
+        .saturating_add(day_of_year);
+    era.saturating_mul(146_097)
+        .saturating_add(day_of_era)
+        .saturating_sub(719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::{days_from_civil, weekday, TimeZone};
+
+    /// The 1 of July 2024 at 12:00 UTC
+    const SUMMER: i64 = 1_719_835_200;
+    /// The 15 of January 2024 at 12:00 UTC
+    const WINTER: i64 = 1_705_320_000;
+    /// The timezone file of America/Toronto, from the database
+    static TORONTO_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/America/Toronto");
+
+    #[test]
+    fn test_days_from_civil() -> Result<(), Box<dyn Error>> {
+        let table = vec![
+            ((1970, 1, 1), 0),
+            ((2000, 3, 1), 11_017),
+            ((2024, 2, 29), 19_782),
+            ((1969, 12, 31), -1),
+        ];
+        for ((year, month, day), expected) in table {
+            let days = days_from_civil(year, month, day);
+            if days != expected {
+                return Err(From::from(format!(
+                    "Test failed: {}-{}-{} gave {}",
+                    year, month, day, days
+                )));
+            }
+        }
+        // The 1 of July 2024 was a Monday
+        if weekday(days_from_civil(2024, 7, 1)) == 1 {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to give the day of the week"))
+        }
+    }
+
+    #[test]
+    fn test_fixed_offset() -> Result<(), Box<dyn Error>> {
+        let india = TimeZone::new("+05:30")?;
+        let utc = TimeZone::new("UTC")?;
+        if india.offset_at(SUMMER) == 19_800
+            && TimeZone::new("-03:00")?.offset_at(WINTER) == -10_800
+            && utc.local_time(SUMMER) == SUMMER
+            && TimeZone::new("+24:00").is_err()
+            && TimeZone::new("../../etc/shadow").is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to use a fixed offset"))
+        }
+    }
+
+    #[test]
+    fn test_file() -> Result<(), Box<dyn Error>> {
+        let toronto = TimeZone::from_file("America/Toronto", TORONTO_PATH)?;
+        // The 10 of March 2024 at 2:00 EST and the 3 of November 2024 at 2:00 EDT
+        let start = 1_710_054_000;
+        let end = 1_730_613_600;
+        let offsets = [
+            toronto.offset_at(WINTER),
+            toronto.offset_at(SUMMER),
+            toronto.offset_at(start - 1),
+            toronto.offset_at(start),
+            toronto.offset_at(end - 1),
+            toronto.offset_at(end),
+        ];
+        if offsets == [-18_000, -14_400, -18_000, -14_400, -14_400, -18_000]
+            && TimeZone::from_file(
+                "Test/Manifest",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+            )
+            .is_err()
+            && TimeZone::new("Nowhere/Atlantis").is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "Test failed to use the timezone file: {:?}",
+                offsets
+            )))
+        }
+    }
+}
@@ -0,0 +1,76 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use log::{debug, warn};
+
+use crate::{dir::Dir, session, signature::TokenKey, tty};
+
+/// `verify_path` analyze if the token exist, and it's valid, then it returns a bool for the result.
+/// A token that isn't signed by `key`, can't be trusted, or that was created with the password of another account, is considered non-existent
+#[must_use]
+pub fn verify_path(dir: &Dir, tty: &tty::Terminal, account: &str, key: &TokenKey) -> bool {
+    let token_name = session::token_name(&tty.terminal_name);
+
+    // Read the token file and return false if invalid or expired
+    debug!("Token will be read from {} and validate", token_name);
+    let token = match session::read_token_file(dir, &token_name, key) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            debug!("Token was non-existent");
+            return false;
+        }
+        Err(err) => {
+            // A token that can't be trusted was already reported as a security event
+            warn!("Token couldn't be read: {}", err);
+            return false;
+        }
+    };
+    if token
+        .verify_token(&tty.terminal_name, &tty.terminal_uuid, account)
+        .is_err()
+    {
+        debug!("Token was invalid");
+        return false;
+    }
+    debug!("Token was valid");
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, fs};
+
+    use super::{tty::Terminal, verify_path, Dir, TokenKey};
+    use crate::utils;
+
+    #[test]
+    fn test_verify_path_non_existent() -> Result<(), Box<dyn Error>> {
+        let tty = Terminal {
+            terminal_name: String::from("/dev/pts/0"),
+            terminal_uuid: String::from("964045904534593458953"),
+        };
+        let path = utils::test_dir("token_dir")?;
+        let dir = Dir::open(&path, users::get_effective_uid())?;
+        let result = verify_path(&dir, &tty, "root", &TokenKey::new(vec![7; 32])?);
+        fs::remove_dir_all(&path)?;
+        if result {
+            Err(From::from("Test failed: the path should not be valid"))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,286 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+#[cfg(target_os = "macos")]
+use std::{
+    convert::TryFrom,
+    io, mem,
+    os::raw::{c_int, c_void},
+};
+use std::{error::Error, ffi::CStr};
+#[cfg(target_os = "linux")]
+use std::{
+    fs,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+};
+
+use libc::{getsid, isatty, ttyname};
+use log::{debug, error};
+
+use crate::{error::RudoError, utils};
+
+/// Value of the audit session ID when the audit subsystem didn't assign a session
+static AUDIT_SESSION_UNSET: &str = "4294967295";
+
+/// `AuditTerminal` is the `au_tid_addr_t` of macOS, the terminal of an audit session
+#[cfg(target_os = "macos")]
+#[repr(C)]
+#[derive(Default)]
+struct AuditTerminal {
+    /// The device of the terminal
+    _port: i32,
+    /// The type of the address, IPv4 or IPv6
+    _address_type: u32,
+    /// The address of a remote terminal
+    _address: [u32; 4],
+}
+
+/// `AuditInfo` is the `auditinfo_addr_t` of macOS, the audit information of the process
+#[cfg(target_os = "macos")]
+#[repr(C)]
+#[derive(Default)]
+struct AuditInfo {
+    /// The audit user ID, the user that logged in
+    _auid: u32,
+    /// The mask of the events that are audited, for success and failure
+    _mask: [u32; 2],
+    /// The terminal of the session
+    _terminal: AuditTerminal,
+    /// The audit session ID
+    asid: i32,
+    /// The flags of the session
+    _flags: u64,
+}
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    /// Function of macOS that give the audit information of the process, `libc` doesn't declare it
+    fn getaudit_addr(info: *mut AuditInfo, length: c_int) -> c_int;
+}
+
+/// `Terminal` is a struct that contain the name and the identifier of a terminal
+#[derive(Debug)]
+pub struct Terminal {
+    /// Name of the terminal
+    pub terminal_name: String,
+    /// Identifier of the terminal
+    pub terminal_uuid: String,
+}
+
+impl Terminal {
+    /// Create a new instance of the terminal structure to have its name and identifier
+    ///
+    /// # Errors
+    /// Return an error if the standard input isn't a terminal or if its identity couldn't be read
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let terminal_name = get_tty_name()?;
+        let terminal_uuid = terminal_uuid()?;
+
+        Ok(Self {
+            terminal_name,
+            terminal_uuid,
+        })
+    }
+}
+
+/// Safe wrapper to get the name of the current TTY
+/// and return it as a Rust string for further use
+fn get_tty_name() -> Result<String, Box<dyn Error>> {
+    unsafe {
+        // Verify that we are indeed in a terminal
+        if isatty(0) == 0 {
+            error!("{}", RudoError::NoTty);
+            return Err(Box::new(RudoError::NoTty));
+        }
+        // Transform the File Descriptor to a c_char
+        let ttyname_c = ttyname(0);
+
+        // Verify that there is indeed a c_char
+        if ttyname_c.is_null() {
+            error!("Couldn't transform File Descriptor to c_char for ttyname");
+            return Err(From::from(
+                "Couldn't transform File Descriptor to c_char for ttyname",
+            ));
+        }
+        // Transform the c_char to a rust string
+        let ttyname_rust = CStr::from_ptr(ttyname_c).to_string_lossy().into_owned();
+        debug!("Terminal: {} is use", ttyname_rust);
+        Ok(ttyname_rust)
+    }
+}
+
+/// `terminal_uuid` is a function to determine the identity of the use terminal across time
+/// with information that only the kernel can give, so the caller can't forge it.
+/// It combines the boot identifier, the device number of the TTY, the session ID,
+/// the start time of the session leader and the audit session ID.
+/// It works the same on a graphical terminal, a console or an SSH session.
+fn terminal_uuid() -> Result<String, Box<dyn Error>> {
+    let boot_id = utils::read_boot_id()?;
+    let tty_device = tty_device()?;
+    let session_id = session_id()?;
+    let leader_start_time = session_leader_start_time(session_id)?;
+    let audit_session = audit_session_id();
+
+    let uuid = format!(
+        "{}-{}-{}-{}-{}",
+        boot_id, tty_device, session_id, leader_start_time, audit_session
+    );
+    debug!("Terminal identity: {}", uuid);
+    Ok(uuid)
+}
+
+#[cfg(target_os = "linux")]
+/// Extract the device number of the TTY connected to the standard input
+fn tty_device() -> Result<u64, Box<dyn Error>> {
+    // /proc/self/fd/0 resolve to the real device, whatever the name the caller gave it
+    let metadata = fs::metadata("/proc/self/fd/0")?;
+    if !metadata.file_type().is_char_device() {
+        error!("Standard input is not a character device");
+        return Err(From::from("Standard input is not a character device"));
+    }
+    debug!("TTY device number: {}", metadata.rdev());
+    Ok(metadata.rdev())
+}
+
+#[cfg(target_os = "macos")]
+/// Extract the device number of the TTY connected to the standard input, with `fstat` as there is no /proc
+fn tty_device() -> Result<u64, Box<dyn Error>> {
+    let mut stat = unsafe { mem::zeroed::<libc::stat>() };
+    if unsafe { libc::fstat(0, &mut stat) } != 0 {
+        return Err(From::from(format!(
+            "Couldn't read the status of the standard input: {}",
+            io::Error::last_os_error()
+        )));
+    }
+    if stat.st_mode & libc::S_IFMT != libc::S_IFCHR {
+        error!("Standard input is not a character device");
+        return Err(From::from("Standard input is not a character device"));
+    }
+    let device = u64::try_from(stat.st_rdev)?;
+    debug!("TTY device number: {}", device);
+    Ok(device)
+}
+
+/// Safe wrapper to get the session ID of the current process
+fn session_id() -> Result<i32, Box<dyn Error>> {
+    let sid = unsafe { getsid(0) };
+    if sid < 0 {
+        error!("Couldn't extract the session ID of Rudo");
+        return Err(From::from("Couldn't extract the session ID of Rudo"));
+    }
+    debug!("Session ID: {}", sid);
+    Ok(sid)
+}
+
+#[cfg(target_os = "linux")]
+/// Extract the start time of the session leader, so a recycled session ID is not the same session
+fn session_leader_start_time(session_id: i32) -> Result<u64, Box<dyn Error>> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", session_id))?;
+    parse_start_time(&stat)
+}
+
+#[cfg(target_os = "macos")]
+/// Extract the start time of the session leader in microseconds, so a recycled session ID is not the same session
+fn session_leader_start_time(session_id: i32) -> Result<u64, Box<dyn Error>> {
+    let mut info = unsafe { mem::zeroed::<libc::proc_bsdinfo>() };
+    let info_ptr: *mut libc::proc_bsdinfo = &mut info;
+    let size = c_int::try_from(mem::size_of::<libc::proc_bsdinfo>())?;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            session_id,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            info_ptr.cast::<c_void>(),
+            size,
+        )
+    };
+    if written != size {
+        return Err(From::from(format!(
+            "Couldn't read the start time of the session leader {}",
+            session_id
+        )));
+    }
+    Ok(info
+        .pbi_start_tvsec
+        .saturating_mul(1_000_000)
+        .saturating_add(info.pbi_start_tvusec))
+}
+
+#[cfg(target_os = "linux")]
+/// Extract the start time, the 22nd field, of the content of `/proc/<pid>/stat`
+fn parse_start_time(stat: &str) -> Result<u64, Box<dyn Error>> {
+    // The name of the program is between parenthesis and can contain space or parenthesis,
+    // so the fields are counted after the last one
+    let fields = match stat.rfind(')') {
+        Some(index) => stat.get(index..).unwrap_or_default(),
+        None => return Err(From::from("Couldn't find the program name in stat")),
+    };
+    // The first element is the parenthesis, then the fields begin at the 3rd one
+    match fields.split_whitespace().nth(20) {
+        Some(start_time) => Ok(start_time.parse()?),
+        None => Err(From::from("Couldn't find the start time in stat")),
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Extract the audit session ID that the kernel assign at login
+fn audit_session_id() -> String {
+    // Kernel without audit support don't have the file, consider it unset
+    let audit_session = match fs::read_to_string("/proc/self/sessionid") {
+        Ok(audit_session) => audit_session.trim().to_owned(),
+        Err(_) => AUDIT_SESSION_UNSET.to_owned(),
+    };
+    debug!("Audit session ID: {}", audit_session);
+    audit_session
+}
+
+#[cfg(target_os = "macos")]
+/// Extract the audit session ID that the kernel assign at login, with `getaudit_addr`
+fn audit_session_id() -> String {
+    let mut info = AuditInfo::default();
+    let info_ptr: *mut AuditInfo = &mut info;
+    let audit_session = match c_int::try_from(mem::size_of::<AuditInfo>()) {
+        Ok(size) if unsafe { getaudit_addr(info_ptr, size) } == 0 => info.asid.to_string(),
+        _ => AUDIT_SESSION_UNSET.to_owned(),
+    };
+    debug!("Audit session ID: {}", audit_session);
+    audit_session
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::{parse_start_time, Error};
+
+    #[test]
+    fn test_parse_start_time() -> Result<(), Box<dyn Error>> {
+        let stat = "1234 (a) b) S 1 1234 1234 34816 1234 4194560 1 0 0 0 0 0 0 0 20 0 1 0 98765 0";
+        if parse_start_time(stat)? == 98765 {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to extract the start time"))
+        }
+    }
+
+    #[test]
+    fn test_parse_start_time_truncated() -> Result<(), Box<dyn Error>> {
+        if parse_start_time("1234 (bash) S 1 1234").is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: truncated stat should be refused"))
+        }
+    }
+}
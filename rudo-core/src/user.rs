@@ -0,0 +1,100 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::error::Error;
+
+use log::debug;
+use users::{Group, Users, UsersCache};
+
+/// Put the data of the actual user in a structure for later use
+#[derive(Debug)]
+pub struct User {
+    /// The Unix username of the actual user
+    pub username: String,
+    /// The Unix groups the user is part of
+    group: Vec<Group>,
+}
+
+impl User {
+    /// Function to create the user structure with all it's data
+    ///
+    /// # Errors
+    /// Return an error if the user or its groups couldn't be found
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        // Create the user, and it's data for later use
+        debug!("Begin user data creation");
+        let userscache = UsersCache::new();
+        let uid = userscache.get_current_uid();
+        match userscache.get_user_by_uid(uid) {
+            Some(user) => Self::from_unix_user(&user),
+            None => Err(From::from("Couldn't extract user from its UID")),
+        }
+    }
+    /// Function to create the user structure of another user than the actual one, from its name
+    ///
+    /// # Errors
+    /// Return an error if the user or its groups couldn't be found
+    pub fn from_name(username: &str) -> Result<Self, Box<dyn Error>> {
+        debug!("Begin user data creation of {}", username);
+        match users::get_user_by_name(username) {
+            Some(user) => Self::from_unix_user(&user),
+            None => Err(From::from(format!(
+                "{} isn't a real unix username",
+                username
+            ))),
+        }
+    }
+    /// Function to extract the name and the groups of a Unix user
+    fn from_unix_user(user: &users::User) -> Result<Self, Box<dyn Error>> {
+        let username = match user.name().to_str() {
+            Some(name) => name.to_owned(),
+            None => return Err(From::from("Couldn't convert &str to string")),
+        };
+        let group = match user.groups() {
+            Some(group) => group,
+            None => {
+                return Err(From::from(
+                    "Couldn't extract group membership from user data",
+                ))
+            }
+        };
+        Ok(Self { username, group })
+    }
+    /// Function that give the name of the Unix groups the user is part of
+    #[must_use]
+    pub fn group_names(&self) -> Vec<String> {
+        self.group
+            .iter()
+            .filter_map(|group| group.name().to_str().map(str::to_owned))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, User};
+
+    #[test]
+    fn test_from_name() -> Result<(), Box<dyn Error>> {
+        let root = User::from_name("root")?;
+        if root.username == "root" && User::from_name("rudo-no-such-user").is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to find the user from its name"))
+        }
+    }
+}
@@ -0,0 +1,375 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    convert::TryFrom,
+    error::Error,
+    ffi::CStr,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt},
+    path::Path,
+    time::Duration,
+};
+
+#[cfg(target_os = "macos")]
+use std::{ffi::c_void, ptr};
+
+#[cfg(target_os = "linux")]
+use libc::CLOCK_BOOTTIME;
+use libc::{clock_gettime, gethostname, kill, pid_t, timespec, EPERM, O_NOFOLLOW, S_IFMT, S_IFREG};
+#[cfg(target_os = "macos")]
+use libc::{sysctlbyname, CLOCK_MONOTONIC};
+use log::debug;
+
+/// The digits use to write a number in hexadecimal
+static HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+/// Path where the kernel give the identifier of the actual boot
+#[cfg(target_os = "linux")]
+static BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+/// Name of the sysctl where the kernel give the identifier of the actual boot
+#[cfg(target_os = "macos")]
+static BOOT_ID_SYSCTL: &[u8] = b"kern.bootsessionuuid\0";
+
+/// The clock that include the time the system was suspended
+#[cfg(target_os = "linux")]
+const BOOT_CLOCK: libc::clockid_t = CLOCK_BOOTTIME;
+/// The clock that include the time the system was suspended, `CLOCK_MONOTONIC` include it on macOS
+#[cfg(target_os = "macos")]
+const BOOT_CLOCK: libc::clockid_t = CLOCK_MONOTONIC;
+
+/// `to_hex` take a slice of bytes and write each of them as two hexadecimal digits
+#[must_use]
+pub(crate) fn to_hex(data: &[u8]) -> String {
+    let mut buffer = String::with_capacity(data.len().saturating_mul(2));
+    for byte in data {
+        for nibble in &[byte >> 4, byte & 0x0f] {
+            if let Some(digit) = HEX_DIGITS.get(usize::from(*nibble)) {
+                buffer.push(char::from(*digit));
+            }
+        }
+    }
+    buffer
+}
+
+/// `from_hex` take a string of hexadecimal digits and return the bytes it represents
+///
+/// # Errors
+/// Return an error if the data isn't valid hexadecimal
+pub(crate) fn from_hex(data: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if data.len() % 2 != 0 || !data.is_ascii() {
+        return Err(From::from("Invalid hexadecimal string"));
+    }
+    let mut buffer = Vec::with_capacity(data.len() / 2);
+    for index in (0..data.len()).step_by(2) {
+        let digits = match data.get(index..index.saturating_add(2)) {
+            Some(digits) => digits,
+            None => return Err(From::from("Invalid hexadecimal string")),
+        };
+        buffer.push(u8::from_str_radix(digits, 16)?);
+    }
+    Ok(buffer)
+}
+
+/// Function that create a file with a path, a mode and with data
+///
+/// # Errors
+/// Return an error if the file couldn't be created or written
+pub(crate) fn create_file(path: &Path, mode: u32, data: &str) -> Result<(), Box<dyn Error>> {
+    // Creating the file
+    debug!("Creating the file");
+    let mut file = File::create(path)?;
+
+    // Write the data in the file
+    debug!("Writing the string in the file");
+    file.write_all(data.as_bytes())?;
+
+    // Sync data to be sure everything is written on drive
+    debug!("Syncing data on drive");
+    file.sync_all()?;
+
+    // Put file permission to 600 to restraint access
+    debug!("Set file permission");
+    let mut perms = file.metadata()?.permissions();
+    perms.set_mode(mode);
+    file.set_permissions(perms)?;
+    Ok(())
+}
+
+/// Function that read a file only if it's a regular file, not a symlink,
+/// owned by `owner` and with exactly the permissions of `mode`.
+/// The verification is done on the opened file, so the file can't be swapped in between
+///
+/// # Errors
+/// Return an error if the file couldn't be read, or if it has the wrong type, owner or mode
+pub(crate) fn read_protected_file(
+    path: &Path,
+    owner: u32,
+    mode: u32,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    // Refuse to follow a symlink at the last component of the path
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NOFOLLOW)
+        .open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.mode() & S_IFMT != S_IFREG {
+        return Err(From::from(format!(
+            "{} is not a regular file",
+            path.display()
+        )));
+    }
+    if metadata.uid() != owner {
+        return Err(From::from(format!(
+            "{} is owned by {} instead of {}",
+            path.display(),
+            metadata.uid(),
+            owner
+        )));
+    }
+    if metadata.mode() & 0o7777 != mode {
+        return Err(From::from(format!(
+            "{} has mode {:o} instead of {:o}",
+            path.display(),
+            metadata.mode() & 0o7777,
+            mode
+        )));
+    }
+    let mut buffer = Vec::new();
+    io::copy(&mut file, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Function that read the identifier of the actual boot, as it changes every time the system start
+///
+/// # Errors
+/// Return an error if the boot identifier couldn't be read or is empty
+#[cfg(target_os = "linux")]
+pub(crate) fn read_boot_id() -> Result<String, Box<dyn Error>> {
+    debug!("Reading the boot identifier from {}", BOOT_ID_PATH);
+    let boot_id = fs::read_to_string(BOOT_ID_PATH)?.trim().to_owned();
+    if boot_id.is_empty() {
+        return Err(From::from("The boot identifier is empty"));
+    }
+    Ok(boot_id)
+}
+
+/// Function that read the identifier of the actual boot, as it changes every time the system start
+///
+/// # Errors
+/// Return an error if the boot identifier couldn't be read or is empty
+#[cfg(target_os = "macos")]
+pub(crate) fn read_boot_id() -> Result<String, Box<dyn Error>> {
+    debug!("Reading the boot identifier from the sysctl kern.bootsessionuuid");
+    let mut buffer = [0_u8; 64];
+    let mut len = buffer.len();
+    let name = CStr::from_bytes_with_nul(BOOT_ID_SYSCTL)?;
+    if unsafe {
+        sysctlbyname(
+            name.as_ptr(),
+            buffer.as_mut_ptr().cast::<c_void>(),
+            &mut len,
+            ptr::null_mut(),
+            0,
+        )
+    } != 0
+    {
+        return Err(From::from("Couldn't read the boot identifier"));
+    }
+    // The value end with a null byte, that isn't part of the identifier
+    let value = buffer.get(..len).unwrap_or_default();
+    let end = value.iter().position(|&byte| byte == 0).unwrap_or(len);
+    let boot_id = String::from_utf8(value.get(..end).unwrap_or_default().to_vec())?;
+    if boot_id.is_empty() {
+        return Err(From::from("The boot identifier is empty"));
+    }
+    Ok(boot_id)
+}
+
+/// Safe wrapper to read the monotonic clock that include the time the system was suspended.
+/// Unlike the wall-clock, it can't be changed by setting the date of the system
+///
+/// # Errors
+/// Return an error if the clock couldn't be read
+pub(crate) fn boottime() -> Result<Duration, Box<dyn Error>> {
+    let mut time = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { clock_gettime(BOOT_CLOCK, &mut time) } != 0 {
+        return Err(From::from("Couldn't read the boot time clock"));
+    }
+    let secs = u64::try_from(time.tv_sec)?;
+    let nanos = u32::try_from(time.tv_nsec)?;
+    Ok(Duration::new(secs, nanos))
+}
+
+/// Safe wrapper to know if a process exists, even if it belongs to another user
+#[must_use]
+pub(crate) fn process_exists(pid: pid_t) -> bool {
+    // The signal 0 is never sent, only the existence of the process is verified
+    let found = unsafe { kill(pid, 0) } == 0;
+    found || io::Error::last_os_error().raw_os_error() == Some(EPERM)
+}
+
+/// Safe wrapper to get the name of the host, as given by the kernel
+///
+/// # Errors
+/// Return an error if the name couldn't be read or isn't valid UTF-8
+pub fn hostname() -> Result<String, Box<dyn Error>> {
+    let mut buffer = [0; 256];
+    // The last byte is never given to the kernel, so the name always end with a null byte
+    let len = buffer.len().saturating_sub(1);
+    if unsafe { gethostname(buffer.as_mut_ptr(), len) } != 0 {
+        return Err(From::from("Couldn't read the name of the host"));
+    }
+    let name = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+    Ok(name.to_str()?.to_owned())
+}
+
+#[cfg(test)]
+/// Function to create an empty directory for a test in the temporary directory, so the tests never write elsewhere
+pub(crate) fn test_dir(name: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    use std::{env, fs::DirBuilder, os::unix::fs::DirBuilderExt, process};
+
+    let path = env::temp_dir().join(format!("rudo-{}-{}", name, process::id()));
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    DirBuilder::new().mode(0o700).create(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryFrom, env, error::Error, fs, process};
+
+    use std::os::unix::fs::symlink;
+
+    use super::{
+        boottime, create_file, from_hex, hostname, process_exists, read_boot_id,
+        read_protected_file, test_dir, to_hex,
+    };
+
+    #[test]
+    fn test_hex() -> Result<(), Box<dyn Error>> {
+        let data = vec![0, 15, 16, 255];
+        if to_hex(&data) == "000f10ff" && from_hex("000f10ff")? == data {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to convert hexadecimal correctly"))
+        }
+    }
+
+    #[test]
+    fn test_from_hex_invalid() -> Result<(), Box<dyn Error>> {
+        if from_hex("abc").is_err() && from_hex("zz").is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: invalid hexadecimal was accepted"))
+        }
+    }
+
+    #[test]
+    fn test_create_file() -> Result<(), Box<dyn Error>> {
+        let path = env::temp_dir().join(format!("rudo_test_{}.txt", process::id()));
+        create_file(&path, 0o600, "1234")?;
+        let data = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        if data == "1234" {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed! file was not create with the right data",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_read_boot_id() -> Result<(), Box<dyn Error>> {
+        if read_boot_id()? == read_boot_id()? {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: boot identifier should be stable"))
+        }
+    }
+
+    #[test]
+    fn test_boottime() -> Result<(), Box<dyn Error>> {
+        let first = boottime()?;
+        let second = boottime()?;
+        if first <= second {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: boot time clock went backward"))
+        }
+    }
+
+    #[test]
+    fn test_process_exists() -> Result<(), Box<dyn Error>> {
+        let pid = i32::try_from(process::id())?;
+        // A pid is never higher than 2^22 on Linux
+        if process_exists(pid) && !process_exists(999_999_999) {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to find if a process exists"))
+        }
+    }
+
+    #[test]
+    fn test_hostname() -> Result<(), Box<dyn Error>> {
+        let name = hostname()?;
+        if !name.is_empty() && !name.contains('\0') {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to read the name of the host"))
+        }
+    }
+
+    #[test]
+    fn test_read_protected_file() -> Result<(), Box<dyn Error>> {
+        let dir = test_dir("protected")?;
+        let path = dir.join("protected.txt");
+        create_file(&path, 0o600, "1234")?;
+        let owner = users::get_effective_uid();
+        let good = read_protected_file(&path, owner, 0o600)?;
+        let bad_mode = read_protected_file(&path, owner, 0o400);
+        let bad_owner = read_protected_file(&path, owner.wrapping_add(1), 0o600);
+        fs::remove_dir_all(&dir)?;
+        if good == b"1234" && bad_mode.is_err() && bad_owner.is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to verify the owner and the mode"))
+        }
+    }
+
+    #[test]
+    fn test_read_protected_file_symlink() -> Result<(), Box<dyn Error>> {
+        let dir = test_dir("protected_symlink")?;
+        let path = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        create_file(&path, 0o600, "1234")?;
+        symlink(&path, &link)?;
+        let result = read_protected_file(&link, users::get_effective_uid(), 0o600);
+        fs::remove_dir_all(&dir)?;
+        if result.is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a symlink was followed"))
+        }
+    }
+}
@@ -17,98 +17,461 @@
  */
 use std::error::Error;
 
-use log::{debug, info};
-use pam_client::{conv_cli::Conversation, Context, Flag};
+use log::{debug, info, warn};
+use rudo_core::{
+    config::{self, AuthAs, PasswordConf, RudoConf},
+    error::RudoError,
+    hostname, session, verify_path, Dir, Terminal, TokenKey, SESSION_PATH,
+};
 
-use crate::{config, pwd, session, token, tty, user, SESSION_PATH};
+use crate::{
+    authenticator::{AuthFailure, Authenticator},
+    conv::{Conversation, PasswordSource, Placeholders},
+    lockout, pwd,
+};
 
-/// Function to verify if the user is authorized before using Pam
-pub(crate) fn authentification(
-    userconf: &config::UserConf,
-    userdata: &user::User,
-) -> Result<(), Box<dyn Error>> {
-    // Verify that the user is authorized to run Rudo
-    debug!("Starting verification of {}", &userconf.username);
-    userdata.verify_user(&userconf.username)?;
+/// The prompt used when the password of another account than the one of the user is asked
+static DEFAULT_ACCOUNT_PROMPT: &str = "[rudo] password of %p: ";
 
-    // Verify that the user is a member of the privilege group for privilege access
-    debug!(
-        "User was approved, starting group verification of {}",
-        userconf.group
-    );
-    userdata.verify_group(&userconf.group)?;
-
-    Ok(())
+/// `AuthOptions` are the options of the command-line that change how the user is authenticated,
+/// with the terminal of the user and the backend that authenticate it
+pub(crate) struct AuthOptions<B> {
+    /// Fail instead of asking for a password
+    pub(crate) non_interactive: bool,
+    /// Where the password is read
+    pub(crate) password_source: PasswordSource,
+    /// The prompt of the command-line, that has the priority over the configuration
+    pub(crate) prompt: Option<String>,
+    /// A login shell is asked, so the Pam service of a login is used
+    pub(crate) login: bool,
+    /// The terminal of the user, the session token is tied to it. There is no token without a terminal
+    pub(crate) terminal: Option<Terminal>,
+    /// Create the backend with the Pam service, the account whose password is asked and the conversation
+    pub(crate) backend: B,
 }
 
-/// Function to verify that the user is authorized to run Rudo with Pam and if a precedent session is valid
-pub(crate) fn authentification_pam(
-    conf: &config::Config,
-    userconf: &config::UserConf,
-    userdata: &user::User,
-) -> Result<Context<Conversation>, Box<dyn Error>> {
-    // Create the Pam context
-    debug!("Creating Pam context for Rudo");
-    let mut context = Context::new(
-        "rudo",
-        Some(&userdata.username), // Give the name of the actual user
-        Conversation::new(),
-    )?;
+/// `Applicant` is the user that ask the authorization to run a command
+pub(crate) struct Applicant<'a> {
+    /// The username of the user
+    pub(crate) username: &'a str,
+    /// A Boolean to determine if the user can't be asked anything, not even to change its password
+    pub(crate) non_interactive: bool,
+}
 
+/// Function to find the terminal of the user. It's only required when the password is asked on it
+pub(crate) fn find_terminal(
+    password_source: &PasswordSource,
+) -> Result<Option<Terminal>, Box<dyn Error>> {
     // Extract the Terminal name and identifier
-    let tty = tty::Terminal::new()?;
+    let terminal = if matches!(password_source, PasswordSource::Terminal) {
+        Some(Terminal::new()?)
+    } else {
+        Terminal::new().ok()
+    };
+    if let Some(ref tty) = terminal {
+        debug!("TTY name is: {}", tty.terminal_name);
+        debug!("Terminal UUID is {}", tty.terminal_uuid);
+    }
+    Ok(terminal)
+}
 
-    debug!("TTY name is: {}", tty.terminal_name);
-    debug!("Terminal UUID is {}", tty.terminal_uuid);
+/// Function to verify that the user is authorized to run Rudo with the backend, Pam, and if a precedent session is valid
+pub(crate) fn authentification_pam<A, B>(
+    conf: &config::Config,
+    password_conf: &PasswordConf,
+    username: &str,
+    options: AuthOptions<B>,
+) -> Result<A, Box<dyn Error>>
+where
+    A: Authenticator,
+    B: FnOnce(&str, &str, Conversation) -> Result<A, Box<dyn Error>>,
+{
+    // Verify that the user has not made too many authentication failures
+    debug!("Verifying that {} is not locked out", username);
+    lockout::verify_lock(username, &conf.rudo)?;
 
-    // Create the token path with the base, the username and the TTY name
-    let token_path = format!(
-        "{}{}{}",
-        SESSION_PATH, &userdata.username, tty.terminal_name
+    // Find the account whose password is asked, as the policy decided
+    let account = password_conf.auth_as.account(username, &conf.rudo.impuser);
+    debug!(
+        "{} will authenticate as {} ({})",
+        username, account, password_conf.auth_as
     );
-    debug!("token_path has been created: {}", token_path);
 
-    // Verify that token_path is valid and that the session is not expired,
-    // then pass the result.
-    debug!("Verifying token_path validity and extracting result");
-    let result = token::verify_path(&token_path, &tty)?;
+    // Replace the placeholders of the custom prompt, if there is one
+    let prompt = password_prompt(
+        options.prompt.as_deref(),
+        password_conf,
+        username,
+        &conf.rudo.impuser,
+    )?;
+
+    // Create the Pam context, with the name of the account that must be authenticated
+    let mut authenticator = (options.backend)(
+        conf.rudo.pam_service(options.login),
+        account,
+        Conversation::new(options.password_source, prompt)?,
+    )?;
+
+    // Open the directory of the token of the user, with the key that sign the token and the terminal it is tied to
+    let token_store = if let Some(terminal) = options.terminal {
+        let key = TokenKey::load()?;
+        Some((open_token_dir(username, &key)?, key, terminal))
+    } else {
+        debug!("No terminal, the session token will not be used");
+        None
+    };
+
+    // Verify that the token is valid and that the session is not expired,
+    // then pass the result. Without a terminal, there is no token to verify.
+    debug!("Verifying token validity and extracting result");
+    let result = token_store
+        .as_ref()
+        .map_or(false, |(token_dir, key, terminal)| {
+            verify_path(token_dir, terminal, account, key)
+        });
 
     debug!("Asking for password if token is invalid or non-existent");
-    if !result {
+    if !result && password_conf.required && options.non_interactive {
         info!(
-            "{} demand authorization to use Rudo, password will be asked",
-            userdata.username
+            "{} demand authorization to use Rudo, but a password is required in non-interactive mode",
+            username
         );
-        // Password will be asked to validate the authorization
-        pwd::password_input(userconf.password, &mut context)?;
+        return Err(Box::new(RudoError::PasswordRequired));
+    }
+    if !result {
         info!(
-            "{} has given is password that was validated by Pam",
-            userdata.username
+            "{} demand authorization to use Rudo, password will be asked",
+            username
         );
+        let applicant = Applicant {
+            username,
+            non_interactive: options.non_interactive,
+        };
+        verify_account(&mut authenticator, &conf.rudo, password_conf, &applicant)?;
 
-        // Validate the account (is not locked, expired, etc.)
-        debug!("Validate the account of {}", userdata.username);
-        context.acct_mgmt(Flag::DISALLOW_NULL_AUTHTOK)?;
+        // Create token with all the necessary information, if there is a terminal to tie it to
+        if let Some((token_dir, key, terminal)) = token_store {
+            let token =
+                session::Token::new(&terminal.terminal_name, &terminal.terminal_uuid, account);
+            debug!(
+                "Token was created for {} with UUID: {}",
+                terminal.terminal_name, terminal.terminal_uuid
+            );
 
-        // Create the run directory where the token will be written
-        debug!("Creating the directory of the token in /run");
-        session::create_dir_run(&userdata.username)?;
-
-        // Create token with all the necessary information
-        let token = session::Token::new(&tty.terminal_name, &tty.terminal_uuid);
-        debug!(
-            "Token was created for {} with UUID: {}",
-            tty.terminal_name, tty.terminal_uuid
-        );
-
-        // Write the token to file
-        debug!("Token will be written to {}", token_path);
-        token?.create_token_file(&userdata.username)?;
+            // Write the token to file
+            debug!("Token will be written in {}{}", SESSION_PATH, username);
+            token?.create_token_file(&token_dir, &key)?;
+        }
     }
 
     // Change the user to have privilege access accordingly to the configuration of the user
-    context.set_user(Some(conf.rudo.impuser.as_str()))?;
+    authenticator.set_user(&conf.rudo.impuser)?;
     info!("User was change to: {}", conf.rudo.impuser);
 
-    Ok(context)
+    Ok(authenticator)
+}
+
+/// Function to open the directory of the token of the user, creating it if necessary, and remove its stale token
+fn open_token_dir(username: &str, key: &TokenKey) -> Result<Dir, Box<dyn Error>> {
+    debug!("Opening the directory of the token in {}", SESSION_PATH);
+    let token_dir = session::create_dir_run(username)?;
+    // Remove the stale token of the user, an error there must not stop Rudo
+    match session::prune_tokens(&token_dir, key) {
+        Ok(count) => debug!("{} stale token removed", count),
+        Err(err) => warn!("Couldn't remove the stale token: {}", err),
+    }
+    Ok(token_dir)
+}
+
+/// Function to ask the password of the account whose password is asked, then validate the account
+/// (is not locked, expired, etc.). The failures of the password are counted for the lockout,
+/// and an expired password is changed, unless the user can't be asked to change it
+pub(crate) fn verify_account(
+    authenticator: &mut dyn Authenticator,
+    rudoconf: &RudoConf,
+    password_conf: &PasswordConf,
+    applicant: &Applicant<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let username = applicant.username;
+    let account = password_conf.auth_as.account(username, &rudoconf.impuser);
+
+    // Password will be asked to validate the authorization, each attempt is counted for the lockout
+    pwd::password_input(password_conf, authenticator, username, rudoconf)?;
+    info!(
+        "{} has given the password of {} that was validated by Pam",
+        username, account
+    );
+
+    // Validate the account (is not locked, expired, etc.)
+    debug!("Validate the account of {}", account);
+    match authenticator.acct_mgmt() {
+        Err(AuthFailure::PasswordExpired) if applicant.non_interactive => {
+            // Changing the password would ask for it, the user must run Rudo interactively
+            info!(
+                "The password of {} has expired, but it can't be changed in non-interactive mode",
+                account
+            );
+            return Err(Box::new(RudoError::PasswordRequired));
+        }
+        Err(AuthFailure::PasswordExpired) => {
+            // The password aged out, it's changed now instead of asking the user to run passwd
+            info!(
+                "The password of {} has expired and must be changed",
+                account
+            );
+            eprintln!(
+                "The password of {} has expired and must be changed",
+                account
+            );
+            authenticator
+                .change_expired_password()
+                .map_err(|failure| RudoError::AuthFailed(failure.to_string()))?;
+            info!("The expired password of {} was changed", account);
+        }
+        Err(failure) => return Err(Box::new(RudoError::AuthFailed(failure.to_string()))),
+        Ok(()) => {}
+    }
+    Ok(())
+}
+
+/// Function to create the prompt of the password, with its placeholders replaced.
+/// The prompt of Pam doesn't say whose password it asks, so it's replaced when it isn't the one of the user
+fn password_prompt(
+    cli_prompt: Option<&str>,
+    password_conf: &PasswordConf,
+    user: &str,
+    target: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let template = match cli_prompt.or(password_conf.prompt.as_deref()) {
+        Some(template) => template,
+        None if password_conf.auth_as == AuthAs::Invoker => return Ok(None),
+        None => DEFAULT_ACCOUNT_PROMPT,
+    };
+    let host = hostname()?;
+    let placeholders = Placeholders {
+        user,
+        target,
+        host: &host,
+        password_user: password_conf.auth_as.account(user, target),
+    };
+    Ok(Some(placeholders.expand(template)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, time::Duration};
+
+    use super::{authentification_pam, verify_account, Applicant, AuthOptions};
+    use rudo_core::{
+        alias::Aliases,
+        config::{Config, PasswordConf, RudoConf, UserConf},
+        error::{self, RudoError},
+    };
+
+    use crate::{
+        authenticator::{AuthFailure, Authenticator, FakeAuthenticator},
+        conv::{Conversation, PasswordSource},
+    };
+
+    /// Create the configuration of the tests, without lockout or delay between attempts
+    fn test_conf() -> (RudoConf, PasswordConf) {
+        let rudoconf = RudoConf {
+            lockout_attempts: 0,
+            ..RudoConf::default()
+        };
+        let mut password_conf = rudoconf.password_conf(&UserConf::default());
+        password_conf.delay = Duration::from_secs(0);
+        (rudoconf, password_conf)
+    }
+
+    /// Run the authentication of alice with a fake backend, without terminal, so without token
+    fn authenticate_alice(
+        password_conf: &PasswordConf,
+        non_interactive: bool,
+        answers: &'static [&'static str],
+    ) -> Result<FakeAuthenticator, Box<dyn Error>> {
+        let (rudoconf, _) = test_conf();
+        let conf = Config {
+            rudo: rudoconf,
+            user: Vec::new(),
+            aliases: Aliases::default(),
+        };
+        let options = AuthOptions {
+            non_interactive,
+            password_source: PasswordSource::Stdin,
+            prompt: None,
+            login: false,
+            terminal: None,
+            backend: |_: &str, account: &str, _: Conversation| {
+                Ok(FakeAuthenticator::new(account, "secret", answers))
+            },
+        };
+        authentification_pam(&conf, password_conf, "alice", options)
+    }
+
+    /// The interactive user of the tests
+    static ALICE: Applicant<'static> = Applicant {
+        username: "alice",
+        non_interactive: false,
+    };
+
+    #[test]
+    fn test_authentification_success() -> Result<(), Box<dyn Error>> {
+        let (_, password_conf) = test_conf();
+        let fake = authenticate_alice(&password_conf, false, &["secret"])?;
+        if fake.attempts == 1 && fake.user == "root" {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to authenticate alice as root"))
+        }
+    }
+
+    #[test]
+    fn test_authentification_non_interactive() -> Result<(), Box<dyn Error>> {
+        let (_, password_conf) = test_conf();
+        let result = authenticate_alice(&password_conf, true, &["secret"]);
+        let code = result
+            .as_ref()
+            .err()
+            .map(|err| error::exit_code(err.as_ref()));
+        if code == Some(3) {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: the password was asked in non-interactive mode",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_authentification_bad_password() -> Result<(), Box<dyn Error>> {
+        let (_, password_conf) = test_conf();
+        let result = authenticate_alice(&password_conf, false, &["one", "two", "three"]);
+        let code = result
+            .as_ref()
+            .err()
+            .map(|err| error::exit_code(err.as_ref()));
+        if code == Some(6) {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a bad password was accepted"))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_success() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake = FakeAuthenticator::new("alice", "secret", &["wrong", "secret"]);
+        verify_account(&mut fake, &rudoconf, &password_conf, &ALICE)?;
+        fake.set_user("root")?;
+        fake.open_session()?;
+        if fake.attempts == 2 && fake.session_open && fake.env_list().len() == 1 {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to authenticate the user"))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_bad_password() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake =
+            FakeAuthenticator::new("alice", "secret", &["one", "two", "three", "secret"]);
+        let result = verify_account(&mut fake, &rudoconf, &password_conf, &ALICE);
+        let code = result
+            .as_ref()
+            .err()
+            .map(|err| error::exit_code(err.as_ref()));
+        if code == Some(6) && fake.attempts == password_conf.tries {
+            Ok(())
+        } else {
+            Err(From::from("Test failed: a bad password was accepted"))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_max_tries() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake = FakeAuthenticator::new("alice", "secret", &["one", "secret"]);
+        fake.max_tries = 1;
+        let result = verify_account(&mut fake, &rudoconf, &password_conf, &ALICE);
+        if result.is_err() && fake.attempts == 1 {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to stop at the limit of the backend",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_locked() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake = FakeAuthenticator::new("alice", "secret", &["secret"]);
+        fake.account_failure = Some(AuthFailure::AccountLocked);
+        match verify_account(&mut fake, &rudoconf, &password_conf, &ALICE) {
+            Err(err)
+                if err.downcast_ref::<RudoError>()
+                    == Some(&RudoError::AuthFailed(
+                        AuthFailure::AccountLocked.to_string(),
+                    )) =>
+            {
+                Ok(())
+            }
+            _ => Err(From::from("Test failed: a locked account was accepted")),
+        }
+    }
+
+    #[test]
+    fn test_verify_account_expired_password() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake = FakeAuthenticator::new("alice", "secret", &["secret"]);
+        fake.account_failure = Some(AuthFailure::PasswordExpired);
+        verify_account(&mut fake, &rudoconf, &password_conf, &ALICE)?;
+        if fake.password_changed {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to change the expired password"))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_expired_password_unchanged() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, password_conf) = test_conf();
+        let mut fake = FakeAuthenticator::new("alice", "secret", &["secret"]);
+        fake.account_failure = Some(AuthFailure::PasswordExpired);
+        fake.change_failure = Some(AuthFailure::Other(String::from("Weak password")));
+        if verify_account(&mut fake, &rudoconf, &password_conf, &ALICE).is_err()
+            && !fake.password_changed
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed: the password was not changed but was accepted",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_verify_account_expired_password_non_interactive() -> Result<(), Box<dyn Error>> {
+        let (rudoconf, mut password_conf) = test_conf();
+        password_conf.required = false;
+        let mut fake = FakeAuthenticator::new("alice", "secret", &[]);
+        fake.account_failure = Some(AuthFailure::PasswordExpired);
+        let applicant = Applicant {
+            username: "alice",
+            non_interactive: true,
+        };
+        match verify_account(&mut fake, &rudoconf, &password_conf, &applicant) {
+            Err(err)
+                if err.downcast_ref::<RudoError>() == Some(&RudoError::PasswordRequired)
+                    && !fake.password_changed =>
+            {
+                Ok(())
+            }
+            _ => Err(From::from(
+                "Test failed: the password was changed in non-interactive mode",
+            )),
+        }
+    }
 }
@@ -0,0 +1,337 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{error::Error, ffi::OsString, fmt};
+
+use log::{debug, error, warn};
+use pam_client::{Flag, ReturnCode};
+
+use crate::{conv::Conversation, pam::Transaction};
+
+/// `Authenticator` is the backend that authenticate the user and open its session, like Pam
+pub(crate) trait Authenticator {
+    /// Ask the password, or any other proof of identity, and verify it
+    fn authenticate(&mut self) -> Result<(), AuthFailure>;
+    /// Verify that the account is valid, that it's not locked or that its password has not expired
+    fn acct_mgmt(&mut self) -> Result<(), AuthFailure>;
+    /// Ask the user to change its password, when it has expired
+    fn change_expired_password(&mut self) -> Result<(), AuthFailure>;
+    /// Change the user of the session, as the command is run as the impersonated user
+    fn set_user(&mut self, user: &str) -> Result<(), Box<dyn Error>>;
+    /// Establish the credentials of the user, then open its session
+    fn open_session(&mut self) -> Result<(), Box<dyn Error>>;
+    /// Close the session, then delete the credentials of the user
+    fn close_session(&mut self) -> Result<(), Box<dyn Error>>;
+    /// The environment variables that the backend give to the command
+    fn env_list(&self) -> Vec<(OsString, OsString)>;
+}
+
+/// `AuthFailure` is the reason the backend refuse the user
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AuthFailure {
+    /// The password was incorrect
+    BadPassword,
+    /// The backend refuse more attempts
+    MaxTries,
+    /// The account is locked or has expired
+    AccountLocked,
+    /// The password has expired and must be changed
+    PasswordExpired,
+    /// Any other error of the backend, with its message
+    Other(String),
+}
+
+impl fmt::Display for AuthFailure {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::BadPassword => write!(formatter, "Authentication failure"),
+            Self::MaxTries => write!(formatter, "Maximum number of tries reached"),
+            Self::AccountLocked => write!(formatter, "The account is locked or has expired"),
+            Self::PasswordExpired => write!(formatter, "The password has expired"),
+            Self::Other(ref message) => write!(formatter, "{}", message),
+        }
+    }
+}
+
+impl Error for AuthFailure {}
+
+impl From<pam_client::Error> for AuthFailure {
+    fn from(err: pam_client::Error) -> Self {
+        match err.code() {
+            // An unknown user is not revealed, it's a bad password like any other
+            ReturnCode::AUTH_ERR | ReturnCode::CRED_INSUFFICIENT | ReturnCode::USER_UNKNOWN => {
+                Self::BadPassword
+            }
+            ReturnCode::MAXTRIES => Self::MaxTries,
+            ReturnCode::ACCT_EXPIRED | ReturnCode::PERM_DENIED => Self::AccountLocked,
+            ReturnCode::NEW_AUTHTOK_REQD => Self::PasswordExpired,
+            _ => Self::Other(err.to_string()),
+        }
+    }
+}
+
+/// `PamAuthenticator` authenticate the user with Pam
+pub(crate) struct PamAuthenticator {
+    /// The transaction of Pam
+    transaction: Transaction,
+    /// A Boolean to determine if the credentials were established, they are deleted after the session
+    credentials: bool,
+    /// A Boolean to determine if the session was opened, it's closed before the credentials are deleted
+    session: bool,
+}
+
+impl PamAuthenticator {
+    /// Create the transaction of Pam for `service` to authenticate `account`
+    pub(crate) fn new(
+        service: &str,
+        account: &str,
+        conversation: Conversation,
+    ) -> Result<Self, Box<dyn Error>> {
+        debug!("Creating Pam context for Rudo with the service {}", service);
+        let transaction = Transaction::new(service, account, conversation)?;
+        Ok(Self {
+            transaction,
+            credentials: false,
+            session: false,
+        })
+    }
+}
+
+impl Authenticator for PamAuthenticator {
+    fn authenticate(&mut self) -> Result<(), AuthFailure> {
+        // The custom prompt only replace the password while it's verified, not when it's changed
+        self.transaction.conversation_mut().set_authenticating(true);
+        let result = self
+            .transaction
+            .call(pam_sys::wrapped::authenticate, Flag::DISALLOW_NULL_AUTHTOK);
+        self.transaction
+            .conversation_mut()
+            .set_authenticating(false);
+        Ok(result?)
+    }
+    fn acct_mgmt(&mut self) -> Result<(), AuthFailure> {
+        Ok(self
+            .transaction
+            .call(pam_sys::wrapped::acct_mgmt, Flag::DISALLOW_NULL_AUTHTOK)?)
+    }
+    fn change_expired_password(&mut self) -> Result<(), AuthFailure> {
+        Ok(self
+            .transaction
+            .call(pam_sys::wrapped::chauthtok, Flag::CHANGE_EXPIRED_AUTHTOK)?)
+    }
+    fn set_user(&mut self, user: &str) -> Result<(), Box<dyn Error>> {
+        self.transaction.set_user(user)
+    }
+    fn open_session(&mut self) -> Result<(), Box<dyn Error>> {
+        // The credentials are established before the session, as the modules of the session may need them
+        self.transaction
+            .call(pam_sys::wrapped::setcred, Flag::ESTABLISH_CRED)
+            .map_err(|err| lifecycle_error(SessionStep::EstablishCredentials, &err))?;
+        self.credentials = true;
+        debug!("Credentials established");
+        // A failure leave the credentials, that are deleted once by close_session
+        self.transaction
+            .call(pam_sys::wrapped::open_session, Flag::NONE)
+            .map_err(|err| lifecycle_error(SessionStep::OpenSession, &err))?;
+        self.session = true;
+        debug!("Session opened");
+        Ok(())
+    }
+    fn close_session(&mut self) -> Result<(), Box<dyn Error>> {
+        // Each step is only tried once, even when the other fail
+        let mut result = Ok(());
+        if self.session {
+            self.session = false;
+            match self
+                .transaction
+                .call(pam_sys::wrapped::close_session, Flag::NONE)
+            {
+                Ok(()) => debug!("Session closed"),
+                Err(err) => result = Err(lifecycle_error(SessionStep::CloseSession, &err)),
+            }
+        }
+        if self.credentials {
+            self.credentials = false;
+            match self
+                .transaction
+                .call(pam_sys::wrapped::setcred, Flag::DELETE_CRED)
+            {
+                Ok(()) => debug!("Credentials deleted"),
+                Err(err) => {
+                    let failure = lifecycle_error(SessionStep::DeleteCredentials, &err);
+                    if result.is_ok() {
+                        result = Err(failure);
+                    }
+                }
+            }
+        }
+        result
+    }
+    fn env_list(&self) -> Vec<(OsString, OsString)> {
+        self.transaction.env_list()
+    }
+}
+
+impl Drop for PamAuthenticator {
+    fn drop(&mut self) {
+        // A session that was not closed, because of an error, is closed there
+        if self.close_session().is_err() {
+            warn!("The session couldn't be closed");
+        }
+    }
+}
+
+/// `SessionStep` is the call to Pam of the lifecycle of the session
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SessionStep {
+    /// Establishing the credentials of the user
+    EstablishCredentials,
+    /// Opening the session
+    OpenSession,
+    /// Closing the session
+    CloseSession,
+    /// Deleting the credentials of the user
+    DeleteCredentials,
+}
+
+impl fmt::Display for SessionStep {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::EstablishCredentials => write!(formatter, "establish the credentials"),
+            Self::OpenSession => write!(formatter, "open the session"),
+            Self::CloseSession => write!(formatter, "close the session"),
+            Self::DeleteCredentials => write!(formatter, "delete the credentials"),
+        }
+    }
+}
+
+/// Function to log the failure of a step of the lifecycle of the session, and create its error
+fn lifecycle_error(step: SessionStep, err: &pam_client::Error) -> Box<dyn Error> {
+    let message = format!("Pam couldn't {}: {}", step, err);
+    error!("{}", message);
+    From::from(message)
+}
+
+#[cfg(test)]
+/// `FakeAuthenticator` is an authenticator in memory for the tests, that don't need Pam or root
+pub(crate) struct FakeAuthenticator {
+    /// The password of the account
+    pub(crate) password: String,
+    /// The answers the user give, one for each attempt
+    pub(crate) answers: Vec<String>,
+    /// The failure of the account, if any
+    pub(crate) account_failure: Option<AuthFailure>,
+    /// The failure when the password is changed, if any
+    pub(crate) change_failure: Option<AuthFailure>,
+    /// A Boolean to determine if the password was changed
+    pub(crate) password_changed: bool,
+    /// The maximum number of attempts the backend accept
+    pub(crate) max_tries: u8,
+    /// The number of attempts made
+    pub(crate) attempts: u8,
+    /// The user of the session
+    pub(crate) user: String,
+    /// A Boolean to determine if the session was opened
+    pub(crate) session_open: bool,
+}
+
+#[cfg(test)]
+impl FakeAuthenticator {
+    /// Create a fake authenticator of `user`, with its password and the answers of the user
+    pub(crate) fn new(user: &str, password: &str, answers: &[&str]) -> Self {
+        Self {
+            password: String::from(password),
+            answers: answers.iter().map(|&answer| String::from(answer)).collect(),
+            account_failure: None,
+            change_failure: None,
+            password_changed: false,
+            max_tries: u8::MAX,
+            attempts: 0,
+            user: String::from(user),
+            session_open: false,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Authenticator for FakeAuthenticator {
+    fn authenticate(&mut self) -> Result<(), AuthFailure> {
+        if self.attempts >= self.max_tries {
+            return Err(AuthFailure::MaxTries);
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        if self.answers.is_empty() {
+            return Err(AuthFailure::Other(String::from("No more answers")));
+        }
+        if self.answers.remove(0) == self.password {
+            Ok(())
+        } else {
+            Err(AuthFailure::BadPassword)
+        }
+    }
+    fn acct_mgmt(&mut self) -> Result<(), AuthFailure> {
+        match self.account_failure {
+            Some(ref failure) => Err(failure.clone()),
+            None => Ok(()),
+        }
+    }
+    fn change_expired_password(&mut self) -> Result<(), AuthFailure> {
+        if let Some(ref failure) = self.change_failure {
+            return Err(failure.clone());
+        }
+        self.password_changed = true;
+        self.account_failure = None;
+        Ok(())
+    }
+    fn set_user(&mut self, user: &str) -> Result<(), Box<dyn Error>> {
+        self.user = String::from(user);
+        Ok(())
+    }
+    fn open_session(&mut self) -> Result<(), Box<dyn Error>> {
+        self.session_open = true;
+        Ok(())
+    }
+    fn close_session(&mut self) -> Result<(), Box<dyn Error>> {
+        self.session_open = false;
+        Ok(())
+    }
+    fn env_list(&self) -> Vec<(OsString, OsString)> {
+        vec![(OsString::from("USER"), OsString::from(&self.user))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::SessionStep;
+
+    #[test]
+    fn test_session_step() -> Result<(), Box<dyn Error>> {
+        if SessionStep::EstablishCredentials.to_string() == "establish the credentials"
+            && SessionStep::OpenSession.to_string() == "open the session"
+            && SessionStep::CloseSession.to_string() == "close the session"
+            && SessionStep::DeleteCredentials.to_string() == "delete the credentials"
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to describe the step of the session",
+            ))
+        }
+    }
+}
@@ -0,0 +1,273 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{error::Error, path::Path, time::SystemTime};
+
+use clap::ArgMatches;
+use log::{debug, info, warn};
+use rudo_core::{
+    config::{self, Validity},
+    policy::{self, Decision, Request},
+    schedule, user, CONFIG_PATH, ROOT_UID,
+};
+
+use crate::run;
+
+/// Function to find the configuration file that is checked, `/etc/rudo.conf` unless `--config` give another one.
+/// As Rudo can read any file, only root can check another file
+pub(crate) fn config_path<'a>(matches: &'a ArgMatches<'_>) -> Result<&'a str, Box<dyn Error>> {
+    match matches.value_of("config") {
+        Some(path) if path != CONFIG_PATH && users::get_current_uid() != ROOT_UID => {
+            Err(From::from("Only root can check another configuration file"))
+        }
+        Some(path) => Ok(path),
+        None => Ok(CONFIG_PATH),
+    }
+}
+
+/// Function to read the configuration file that is checked. Unlike a command, a check never create it
+pub(crate) fn read_config(matches: &ArgMatches<'_>) -> Result<config::Config, Box<dyn Error>> {
+    let path = config_path(matches)?;
+    debug!("Reading {} to check it", path);
+    config::read_config_file(Path::new(path))
+}
+
+/// Function that print the decision of the policy for the request of the user,
+/// or of another user for root, without authenticating or running anything
+pub(crate) fn check(
+    matches: &ArgMatches<'_>,
+    conf: &config::Config,
+    userdata: &user::User,
+) -> Result<(), Box<dyn Error>> {
+    // Only root can see the policy of another user
+    let other_user = match matches.value_of("other-user") {
+        Some(username) if username != userdata.username => {
+            if users::get_current_uid() != ROOT_UID {
+                return Err(From::from(
+                    "Only root can check the request of another user",
+                ));
+            }
+            Some(user::User::from_name(username)?)
+        }
+        _ => None,
+    };
+    let checked_user = other_user.as_ref().unwrap_or(userdata);
+    let path = config_path(matches)?;
+
+    debug!("Checking the request of {}", checked_user.username);
+    let request = run::request(matches, conf, checked_user)?;
+    let decision = policy::evaluate(conf, &request);
+    run::log_inactive(&decision, &checked_user.username, path);
+    info!(
+        "{} checked the request of {}: {}",
+        userdata.username, checked_user.username, decision.reason
+    );
+    print!("{}", report(&request, &decision, path));
+
+    // A denied request has the exit code of the denial
+    decision.enforce()?;
+    Ok(())
+}
+
+/// Function that print the user entries of the configuration file, and highlight the ones that expired, for root only.
+/// An invalid configuration file was already refused when it was read
+pub(crate) fn check_config(conf: &config::Config, path: &str) -> Result<(), Box<dyn Error>> {
+    // The configuration file can only be read by root
+    if users::get_current_uid() != ROOT_UID {
+        return Err(From::from("Only root can check the configuration"));
+    }
+    let now = schedule::unix_time(SystemTime::now()).unwrap_or_default();
+    for (index, userconf) in conf.user.iter().enumerate() {
+        if userconf.validity(now) == Validity::Expired {
+            warn!(
+                "The user entry {} of {} for {} expired, it can be removed",
+                index, path, userconf.username
+            );
+        }
+    }
+    print!("{}", config_report(conf, path, now));
+    Ok(())
+}
+
+/// Function to describe the user entries of the configuration, and if they are in effect at an instant
+fn config_report(conf: &config::Config, path: &str, now: i64) -> String {
+    let mut lines = vec![format!(
+        "{} is valid, with {} user entries",
+        path,
+        conf.user.len()
+    )];
+    let mut expired = 0_usize;
+    for (index, userconf) in conf.user.iter().enumerate() {
+        let period = match (&userconf.not_before, &userconf.not_after) {
+            (Some(not_before), Some(not_after)) => {
+                format!(", from {} until {}", not_before, not_after)
+            }
+            (Some(not_before), None) => format!(", from {}", not_before),
+            (None, Some(not_after)) => format!(", until {}", not_after),
+            (None, None) => String::new(),
+        };
+        let status = match userconf.validity(now) {
+            Validity::Active => "in effect",
+            Validity::Pending => "not in effect yet, it's ignored",
+            Validity::Expired => {
+                expired = expired.saturating_add(1);
+                "EXPIRED, it's ignored"
+            }
+        };
+        lines.push(format!(
+            "user entry {}: {} in {}{}: {}",
+            index, userconf.username, userconf.group, period, status
+        ));
+    }
+    if expired > 0 {
+        lines.push(format!(
+            "{} expired user entries can be removed from {}",
+            expired, path
+        ));
+    }
+    format!("{}\n", lines.join("\n"))
+}
+
+/// Function to describe the decision of the policy for the request, for a human
+fn report(request: &Request, decision: &Decision, path: &str) -> String {
+    let verdict = if decision.is_allowed() {
+        "allowed"
+    } else {
+        "denied"
+    };
+    let rule = decision.rule.map_or_else(
+        || String::from("none, the default rule applies"),
+        |index| format!("user entry {} of {}", index, path),
+    );
+    let command_rule = match decision.command_rule {
+        Some(index) => format!("command {} of the user entry", index),
+        None if decision.is_allowed() => String::from("none, every command is allowed"),
+        None => String::from("none"),
+    };
+    let auth = if !decision.is_allowed() {
+        String::from("none, the request is denied")
+    } else if decision.auth.required {
+        format!(
+            "password of {} (auth_as: {})",
+            decision
+                .auth
+                .auth_as
+                .account(&request.user, &request.target),
+            decision.auth.auth_as
+        )
+    } else {
+        String::from("none")
+    };
+    let digest = decision.digest.as_deref().unwrap_or("none");
+    let env = if decision.env.reset {
+        format!("reset, keeping [{}]", decision.env.keep.join(", "))
+    } else {
+        String::from("kept")
+    };
+
+    format!(
+        "Request: {} as {} on {}: {}\nDecision: {}\nReason: {}\nRule: {}\nCommand rule: {}\nDigest: {}\nAuthentication: {}\nEnvironment: {}\n",
+        request.user,
+        request.target,
+        request.host,
+        policy::command_line(request),
+        verdict,
+        decision.reason,
+        rule,
+        command_rule,
+        digest,
+        auth,
+        env
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, path::PathBuf, time::SystemTime};
+
+    use rudo_core::{
+        config::{AuthAs, Config, UserConf},
+        host::Host,
+        policy::{self, Request},
+    };
+
+    use super::{config_report, report};
+
+    #[test]
+    fn test_report() -> Result<(), Box<dyn Error>> {
+        let mut conf = Config::default();
+        conf.user.push(UserConf {
+            username: String::from("alice"),
+            auth_as: AuthAs::Target,
+            ..UserConf::default()
+        });
+        let request = Request {
+            user: String::from("alice"),
+            groups: vec![String::from("wheel")],
+            host: Host {
+                name: String::from("web1"),
+                ..Host::default()
+            },
+            target: String::from("root"),
+            command: String::from("/usr/bin/systemctl"),
+            args: vec![String::from("restart"), String::from("nginx")],
+            cwd: PathBuf::from("/"),
+            time: SystemTime::now(),
+        };
+        let text = report(
+            &request,
+            &policy::evaluate(&conf, &request),
+            "/etc/rudo.conf",
+        );
+        if text.contains("Request: alice as root on web1: /usr/bin/systemctl restart nginx\n")
+            && text.contains("Decision: allowed\n")
+            && text.contains("Rule: user entry 1 of /etc/rudo.conf\n")
+            && text.contains("Authentication: password of root (auth_as: target)\n")
+        {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "Test failed to describe the decision: {}",
+                text
+            )))
+        }
+    }
+
+    #[test]
+    fn test_config_report() -> Result<(), Box<dyn Error>> {
+        let data = "rudo:\n  impuser: root\nuser:\n  - username: root\n    group: wheel\n    password: true\n    greeting: true\n  - username: alice\n    group: wheel\n    password: true\n    greeting: false\n    not_after: 2024-07-01T00:00:00Z\n  - username: bob\n    group: wheel\n    password: true\n    greeting: false\n    not_before: 2024-08-01T00:00:00Z\n";
+        let conf: Config = serde_yaml::from_str(data)?;
+        // The 15 of July 2024
+        let text = config_report(&conf, "rudo.conf.new", 1_721_001_600);
+        if text.contains("user entry 0: root in wheel: in effect\n")
+            && text.contains(
+                "user entry 1: alice in wheel, until 2024-07-01T00:00:00Z: EXPIRED, it's ignored\n",
+            )
+            && text.contains(
+                "user entry 2: bob in wheel, from 2024-08-01T00:00:00Z: not in effect yet, it's ignored\n",
+            )
+            && text.contains("1 expired user entries can be removed from rudo.conf.new\n")
+        {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "Test failed to describe the configuration: {}",
+                text
+            )))
+        }
+    }
+}
@@ -37,6 +37,12 @@ pub(crate) fn init_command_line() -> ArgMatches<'static> {
                 .required_unless("shell")
                 .conflicts_with("edit")
                 .required_unless("edit")
+                .conflicts_with("gc")
+                .required_unless("gc")
+                .conflicts_with("unlock")
+                .required_unless("unlock")
+                .conflicts_with("check-config")
+                .required_unless("check-config")
                 .index(1) // Be sure that the command is the first, so we don't have to write "-c" to take a command
                 .multiple(true) // To be able to have the command, and it's list of argument
                 .allow_hyphen_values(true) // Should authorize "-" in command
@@ -52,6 +58,12 @@ pub(crate) fn init_command_line() -> ArgMatches<'static> {
                 .required_unless("command")
                 .conflicts_with("edit")
                 .required_unless("edit")
+                .conflicts_with("gc")
+                .required_unless("gc")
+                .conflicts_with("unlock")
+                .required_unless("unlock")
+                .conflicts_with("check-config")
+                .required_unless("check-config")
                 .takes_value(false),
         )
         .arg(
@@ -88,8 +100,116 @@ pub(crate) fn init_command_line() -> ArgMatches<'static> {
                 .required_unless("command")
                 .conflicts_with("shell")
                 .required_unless("shell")
+                .conflicts_with("gc")
+                .required_unless("gc")
+                .conflicts_with("unlock")
+                .required_unless("unlock")
+                .conflicts_with("check-config")
+                .required_unless("check-config")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("non-interactive")
+                .short("n")
+                .long("non-interactive")
+                .value_name("non-interactive")
+                .help("Fail instead of asking for a password")
+                .takes_value(false),
+        )
+        .args(&password_args())
+        .args(&maintenance_args())
+        .args(&check_args())
         .get_matches();
     matches
 }
+
+/// Function to create the options that choose where the password is read
+fn password_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("stdin")
+            .short("S")
+            .long("stdin")
+            .value_name("stdin")
+            .help("Read the password from the standard input instead of the terminal")
+            .conflicts_with("askpass")
+            .takes_value(false),
+        Arg::with_name("askpass")
+            .short("A")
+            .long("askpass")
+            .value_name("askpass")
+            .help("Ask the password with the helper program in RUDO_ASKPASS")
+            .conflicts_with("stdin")
+            .takes_value(false),
+        Arg::with_name("prompt")
+            .short("p")
+            .long("prompt")
+            .value_name("prompt")
+            .help("Replace the password prompt, %u %U %h and %p are the user, the target user, the host and the user whose password is asked")
+            .takes_value(true),
+    ]
+}
+
+/// Function to create the options that maintain Rudo instead of running a command
+fn maintenance_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("gc")
+            .long("gc")
+            .value_name("gc")
+            .help("Remove the expired session tokens of every user")
+            .conflicts_with("command")
+            .conflicts_with("shell")
+            .conflicts_with("edit")
+            .conflicts_with("unlock")
+            .takes_value(false),
+        Arg::with_name("unlock")
+            .long("unlock")
+            .value_name("user")
+            .help("Unlock a user that has made too many authentication failures, for root only")
+            .conflicts_with("command")
+            .conflicts_with("shell")
+            .conflicts_with("edit")
+            .conflicts_with("gc")
+            .takes_value(true),
+    ]
+}
+
+/// Function to create the options that print the decision of the policy, or the configuration, instead of running a command
+fn check_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("check")
+            .long("check")
+            .value_name("check")
+            .help(
+                "Print if the request would be allowed, without authenticating or running anything",
+            )
+            .conflicts_with("gc")
+            .conflicts_with("unlock")
+            .conflicts_with("check-config")
+            .takes_value(false),
+        Arg::with_name("other-user")
+            .short("U")
+            .long("other-user")
+            .value_name("user")
+            .help("The user whose request is checked, root only can check another user")
+            .requires("check")
+            .takes_value(true),
+        Arg::with_name("check-config")
+            .long("check-config")
+            .value_name("check-config")
+            .help("Print the user entries of the configuration file and highlight the expired ones, for root only")
+            .conflicts_with("command")
+            .conflicts_with("shell")
+            .conflicts_with("edit")
+            .conflicts_with("gc")
+            .conflicts_with("unlock")
+            .conflicts_with("check")
+            .takes_value(false),
+        Arg::with_name("config")
+            .long("config")
+            .value_name("file")
+            .help("The configuration file that is checked instead of /etc/rudo.conf, with --check or --check-config")
+            .conflicts_with("gc")
+            .conflicts_with("unlock")
+            .takes_value(true),
+    ]
+}
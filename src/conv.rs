@@ -0,0 +1,270 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    ffi::{CStr, CString},
+    io::{self, BufRead, Write},
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+};
+
+use log::{debug, error};
+use pam_client::{conv_cli, ConversationHandler, ReturnCode};
+
+/// `PasswordSource` is where the conversation take the answers to the questions of Pam
+pub(crate) enum PasswordSource {
+    /// Ask the user on its terminal, without echo for the password
+    Terminal,
+    /// Read a line of the standard input for each question, for pipelines
+    Stdin,
+    /// Run the graphical helper program of `RUDO_ASKPASS` and read its output
+    Askpass(String),
+}
+
+/// `Conversation` pass the questions of Pam to the user with the source it has chosen
+pub(crate) struct Conversation {
+    /// Where the answers are taken
+    source: PasswordSource,
+    /// The prompt that replace the one of Pam when the password is asked
+    prompt: Option<CString>,
+    /// A Boolean to determine if the password is being verified, the only time the prompt is replaced
+    authenticating: bool,
+    /// The conversation of `pam_client` that is used on a terminal
+    terminal: conv_cli::Conversation,
+}
+
+impl Conversation {
+    /// Create the conversation that will take its answers from `source`.
+    /// `custom_prompt` replace the prompt of Pam when the password is asked
+    pub(crate) fn new(
+        source: PasswordSource,
+        custom_prompt: Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let prompt = match custom_prompt {
+            Some(text) => Some(CString::new(text)?),
+            None => None,
+        };
+        Ok(Self {
+            source,
+            prompt,
+            authenticating: false,
+            terminal: conv_cli::Conversation::new(),
+        })
+    }
+    /// Tell the conversation if the password is being verified. The questions asked when it's changed,
+    /// like the new password, keep the prompt of Pam
+    pub(crate) fn set_authenticating(&mut self, authenticating: bool) {
+        self.authenticating = authenticating;
+    }
+    /// Ask the question to the source, `echo` is only respected on a terminal
+    fn prompt(&mut self, question: &CStr, echo: bool) -> Result<CString, ReturnCode> {
+        // Only the password that is verified, asked without echo, has its prompt replaced
+        let prompt = match self.prompt {
+            Some(ref custom) if self.authenticating && !echo => custom.as_c_str(),
+            _ => question,
+        };
+        match self.source {
+            PasswordSource::Terminal if echo => self.terminal.prompt_echo_on(prompt),
+            PasswordSource::Terminal => self.terminal.prompt_echo_off(prompt),
+            PasswordSource::Stdin => read_stdin(prompt),
+            PasswordSource::Askpass(ref program) => run_askpass(program, prompt),
+        }
+    }
+}
+
+impl ConversationHandler for Conversation {
+    fn prompt_echo_on(&mut self, prompt: &CStr) -> Result<CString, ReturnCode> {
+        self.prompt(prompt, true)
+    }
+    fn prompt_echo_off(&mut self, prompt: &CStr) -> Result<CString, ReturnCode> {
+        self.prompt(prompt, false)
+    }
+    fn text_info(&mut self, msg: &CStr) {
+        self.terminal.text_info(msg);
+    }
+    fn error_msg(&mut self, msg: &CStr) {
+        self.terminal.error_msg(msg);
+    }
+}
+
+/// `Placeholders` are the values that replace the placeholders of a custom prompt
+pub(crate) struct Placeholders<'a> {
+    /// The user that invoke Rudo, for `%u`
+    pub(crate) user: &'a str,
+    /// The user that will run the command, for `%U`
+    pub(crate) target: &'a str,
+    /// The name of the host, for `%h`
+    pub(crate) host: &'a str,
+    /// The user whose password is asked, for `%p`
+    pub(crate) password_user: &'a str,
+}
+
+impl Placeholders<'_> {
+    /// Replace the placeholders of the prompt with their values, `%%` give a single `%`
+    pub(crate) fn expand(&self, template: &str) -> String {
+        let mut prompt = String::with_capacity(template.len());
+        let mut chars = template.chars();
+        while let Some(character) = chars.next() {
+            if character != '%' {
+                prompt.push(character);
+                continue;
+            }
+            match chars.next() {
+                Some('u') => prompt.push_str(self.user),
+                Some('U') => prompt.push_str(self.target),
+                Some('h') => prompt.push_str(self.host),
+                Some('p') => prompt.push_str(self.password_user),
+                // An unknown placeholder is kept as it is
+                Some(other) => {
+                    prompt.push('%');
+                    if other != '%' {
+                        prompt.push(other);
+                    }
+                }
+                None => prompt.push('%'),
+            }
+        }
+        prompt
+    }
+}
+
+/// Show the prompt on the error output and read the answer on a line of the standard input
+fn read_stdin(prompt: &CStr) -> Result<CString, ReturnCode> {
+    debug!("Reading the answer of Pam on the standard input");
+    if io::stderr().write_all(prompt.to_bytes()).is_err() {
+        return Err(ReturnCode::CONV_ERR);
+    }
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => {
+            error!("Couldn't read the answer of Pam on the standard input");
+            Err(ReturnCode::CONV_ERR)
+        }
+        Ok(_) => to_answer(line.as_bytes()),
+    }
+}
+
+/// Run the helper program with the prompt as argument and read the answer on its output.
+/// The helper is run as the real user, never as root, since the user choose it
+fn run_askpass(program: &str, prompt: &CStr) -> Result<CString, ReturnCode> {
+    debug!("Running {} to ask the answer of Pam", program);
+    let result = Command::new(program)
+        .arg(prompt.to_string_lossy().as_ref())
+        .uid(users::get_current_uid())
+        .gid(users::get_current_gid())
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output();
+    match result {
+        Ok(output) if output.status.success() => to_answer(&output.stdout),
+        Ok(_) => {
+            error!("{} was cancelled or has failed", program);
+            Err(ReturnCode::CONV_ERR)
+        }
+        Err(err) => {
+            error!("Couldn't run {}: {}", program, err);
+            Err(ReturnCode::CONV_ERR)
+        }
+    }
+}
+
+/// Transform a line to the answer for Pam, without its newline
+fn to_answer(line: &[u8]) -> Result<CString, ReturnCode> {
+    let end = line
+        .iter()
+        .rposition(|&byte| byte != b'\n' && byte != b'\r')
+        .map_or(0, |last| last.saturating_add(1));
+    let answer = line.get(..end).unwrap_or_default();
+    CString::new(answer).map_err(|_| ReturnCode::CONV_ERR)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, ffi::CString};
+
+    use pam_client::ConversationHandler;
+
+    use super::{run_askpass, to_answer, Conversation, PasswordSource, Placeholders};
+
+    #[test]
+    fn test_to_answer() -> Result<(), Box<dyn Error>> {
+        if to_answer(b"secret\n") == Ok(CString::new("secret")?)
+            && to_answer(b"secret\r\n") == Ok(CString::new("secret")?)
+            && to_answer(b"secret") == Ok(CString::new("secret")?)
+            && to_answer(b"sec\0ret\n").is_err()
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to transform the line to an answer"))
+        }
+    }
+
+    #[test]
+    fn test_expand_prompt() -> Result<(), Box<dyn Error>> {
+        let placeholders = Placeholders {
+            user: "alice",
+            target: "root",
+            host: "server",
+            password_user: "bob",
+        };
+        let prompt = placeholders.expand("[rudo] %u as %U on %h, password of %p: %% %x%");
+        if prompt == "[rudo] alice as root on server, password of bob: % %x%" {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to replace the placeholders"))
+        }
+    }
+
+    #[test]
+    fn test_askpass() -> Result<(), Box<dyn Error>> {
+        let prompt = CString::new("secret")?;
+        let answer = run_askpass("echo", &prompt);
+        let failure = run_askpass("false", &prompt);
+        if answer == Ok(prompt) && failure.is_err() {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to read the answer of the helper"))
+        }
+    }
+
+    #[test]
+    fn test_custom_prompt_only_authenticating() -> Result<(), Box<dyn Error>> {
+        // The helper echo its prompt, so the answer is the prompt that was asked
+        let mut conversation = Conversation::new(
+            PasswordSource::Askpass(String::from("echo")),
+            Some(String::from("custom")),
+        )?;
+        let pam_prompt = CString::new("New password: ")?;
+        let custom = CString::new("custom")?;
+        conversation.set_authenticating(true);
+        let password = conversation.prompt_echo_off(&pam_prompt);
+        let login = conversation.prompt_echo_on(&pam_prompt);
+        conversation.set_authenticating(false);
+        let new_password = conversation.prompt_echo_off(&pam_prompt);
+        if password == Ok(custom)
+            && login == Ok(pam_prompt.clone())
+            && new_password == Ok(pam_prompt)
+        {
+            Ok(())
+        } else {
+            Err(From::from(
+                "Test failed to replace the prompt only when the password is verified",
+            ))
+        }
+    }
+}
@@ -0,0 +1,228 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    error::Error,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, info};
+use rudo_core::{audit_event, config::RudoConf, error::RudoError, Dir, ROOT_UID, STATE_PATH};
+use serde::{Deserialize, Serialize};
+
+/// Name of the directory, inside the state directory, where the failures are counted
+static FAILURES_DIR: &str = "failures";
+/// Permissions of the directories that contain the failures, only root can access them
+static DIR_MODE: u32 = 0o700;
+/// Permissions of the file of failures of a user
+static FAILURES_MODE: u32 = 0o600;
+
+/// `Failures` count the authentication failures of a user, that are kept across Rudo invocations
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub(crate) struct Failures {
+    /// Number of failed authentication since the last success
+    count: u8,
+    /// Time of the last failure in seconds since the Unix epoch
+    last_failure: u64,
+}
+
+impl Failures {
+    /// Verify if the user has made too many failures and if the lockout has not passed since the last one
+    fn is_locked(self, attempts: u8, lockout_time: u64, now: u64) -> bool {
+        attempts != 0
+            && self.count >= attempts
+            && now < self.last_failure.saturating_add(lockout_time)
+    }
+    /// Add a failure at `now`. The precedent failures are forgotten if the lockout time has passed
+    fn add_failure(self, lockout_time: u64, now: u64) -> Self {
+        let count = if now >= self.last_failure.saturating_add(lockout_time) {
+            1
+        } else {
+            self.count.saturating_add(1)
+        };
+        Self {
+            count,
+            last_failure: now,
+        }
+    }
+}
+
+/// Function to verify that the user is not locked out of Rudo
+pub(crate) fn verify_lock(username: &str, rudoconf: &RudoConf) -> Result<(), Box<dyn Error>> {
+    if rudoconf.lockout_attempts == 0 {
+        debug!("Lockout is disabled");
+        return Ok(());
+    }
+    let failures = read_failures(&open_failures_dir()?, username)?;
+    let now = now();
+    if failures.is_locked(rudoconf.lockout_attempts, rudoconf.lockout_time, now) {
+        return Err(locked_out(username, rudoconf, failures, now));
+    }
+    Ok(())
+}
+
+/// Function to count an attempt of the user as a failure before its password is verified,
+/// so it stays counted if Rudo is killed during the attempt. A valid password forget it with the others.
+/// The user that has already made too many failures is refused, even between the attempts of an invocation.
+/// Return the number of failures, with this attempt
+pub(crate) fn record_attempt(username: &str, rudoconf: &RudoConf) -> Result<u8, Box<dyn Error>> {
+    if rudoconf.lockout_attempts == 0 {
+        return Ok(0);
+    }
+    let dir = open_failures_dir()?;
+    // The lock is kept until the directory is closed, so the parallel invocations don't lose their failures
+    dir.lock()?;
+    let now = now();
+    let previous = read_failures(&dir, username)?;
+    if previous.is_locked(rudoconf.lockout_attempts, rudoconf.lockout_time, now) {
+        return Err(locked_out(username, rudoconf, previous, now));
+    }
+    let failures = previous.add_failure(rudoconf.lockout_time, now);
+    debug!(
+        "{} has {} authentication failures, with the attempt in progress",
+        username, failures.count
+    );
+    dir.write_file(
+        username,
+        FAILURES_MODE,
+        serde_yaml::to_string(&failures)?.as_bytes(),
+    )?;
+    Ok(failures.count)
+}
+
+/// Function to report that an attempt has failed, the user that reach the limit is locked out of Rudo
+pub(crate) fn attempt_failed(username: &str, rudoconf: &RudoConf, count: u8) {
+    if rudoconf.lockout_attempts != 0 && count == rudoconf.lockout_attempts {
+        audit_event(
+            &format!(
+                "{} is locked out of Rudo for {} seconds",
+                username, rudoconf.lockout_time
+            ),
+            &[
+                ("RUDO_EVENT", "lockout"),
+                ("RUDO_USER", username),
+                ("RUDO_FAILURES", &count.to_string()),
+            ],
+        );
+    }
+}
+
+/// Function to forget the failures of the user, after a success or when root unlock them
+pub(crate) fn reset(username: &str) -> Result<(), Box<dyn Error>> {
+    debug!("Forgetting the authentication failures of {}", username);
+    let dir = open_failures_dir()?;
+    dir.lock()?;
+    dir.remove_file(username)
+}
+
+/// Function to forget the authentication failures after a successful authentication, if the lockout is enabled
+pub(crate) fn forget(username: &str, rudoconf: &RudoConf) -> Result<(), Box<dyn Error>> {
+    if rudoconf.lockout_attempts == 0 {
+        return Ok(());
+    }
+    reset(username)
+}
+
+/// Function for root to unlock a user before the end of the lockout
+pub(crate) fn unlock(username: &str) -> Result<(), Box<dyn Error>> {
+    if users::get_current_uid() != ROOT_UID {
+        return Err(From::from("Only root can unlock a user"));
+    }
+    reset(username)?;
+    info!("{} was unlocked by root", username);
+    Ok(())
+}
+
+/// Open the directory of the failures, creating it and the state directory if necessary
+fn open_failures_dir() -> Result<Dir, Box<dyn Error>> {
+    let state_dir = Dir::open_or_create_path(Path::new(STATE_PATH), ROOT_UID, DIR_MODE)?;
+    state_dir.open_or_create_dir(FAILURES_DIR, DIR_MODE)
+}
+
+/// Create the error of a user that is locked out, and report it
+fn locked_out(username: &str, rudoconf: &RudoConf, failures: Failures, now: u64) -> Box<dyn Error> {
+    let remaining = failures
+        .last_failure
+        .saturating_add(rudoconf.lockout_time)
+        .saturating_sub(now);
+    audit_event(
+        &format!("{} is locked out of Rudo", username),
+        &[
+            ("RUDO_EVENT", "lockout_refused"),
+            ("RUDO_USER", username),
+            ("RUDO_FAILURES", &failures.count.to_string()),
+        ],
+    );
+    Box::new(RudoError::LockedOut(format!(
+        "Too many authentication failures! {} is locked out of Rudo for {} seconds",
+        username, remaining
+    )))
+}
+
+/// Read the failures of the user, none if the file doesn't exist
+fn read_failures(dir: &Dir, username: &str) -> Result<Failures, Box<dyn Error>> {
+    match dir.read_file(username, FAILURES_MODE)? {
+        Some(buffer) => Ok(serde_yaml::from_slice(&buffer)?),
+        None => Ok(Failures::default()),
+    }
+}
+
+/// The actual time in seconds since the Unix epoch.
+/// The wall-clock is used as the lockout must survive a reboot
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Failures};
+
+    #[test]
+    fn test_lockout() -> Result<(), Box<dyn Error>> {
+        let failures = Failures::default()
+            .add_failure(60, 1000)
+            .add_failure(60, 1010)
+            .add_failure(60, 1020);
+        if failures.count == 3
+            && failures.is_locked(3, 60, 1030)
+            && !failures.is_locked(3, 60, 1080)
+            && !failures.is_locked(4, 60, 1030)
+            && !failures.is_locked(0, 60, 1030)
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to lock the user correctly"))
+        }
+    }
+
+    #[test]
+    fn test_failure_forgotten() -> Result<(), Box<dyn Error>> {
+        let failures = Failures::default()
+            .add_failure(60, 1000)
+            .add_failure(60, 1010)
+            .add_failure(60, 1100);
+        if failures.count == 1 && failures.last_failure == 1100 {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to forget the old failures"))
+        }
+    }
+}
@@ -84,42 +84,41 @@ clippy::unnecessary_self_imports       // Prefer clearer code
 // Authorized redundant else to conform to MISRA-C:2004 Rule 14.10 and to not conflict with clippy::else_if_without_else
 #![allow(clippy::redundant_else)]
 
-use std::error::Error;
+use std::{error::Error, process};
+
+use rudo_core::error;
 
 /// Module to authenticate the Unix user with the provided configuration
 mod auth;
+/// Module that abstract the authentication backend, like Pam, so it can be replaced in the tests
+mod authenticator;
+/// Module that print the decision of the policy without running anything
+mod check;
 /// Module to instantiate the command-line interface, and it's options
 mod cli;
-/// Module to extract the command, and it's arguments when user provide one
-mod cmd;
-/// Module that manage the configuration file, and it's options
-mod config;
-/// Module that manage the logs that Rudo create
-mod journal;
+/// Module that implement the Pam conversation, and where it takes the password
+mod conv;
+/// Module that lock the user out of Rudo after too many authentication failures
+mod lockout;
+/// Module that make the calls to Pam, one at a time
+mod pam;
 /// Module that ask for the user password to authenticate him
 mod pwd;
 /// Module that take care of running Rudo
 mod run;
-/// Module that manage the session, and it's validity for the user
-mod session;
-/// Module to verify that the token path exist and return a bool wrap in a result
-mod token;
-/// Module to extract the name of the TTY and to verify its existence
-mod tty;
-/// Module that create user information and all the function with it
-mod user;
-/// Generics Function that can be reused by others modules
-mod utils;
 
-/// The amount of time the session stay valid
-pub(crate) static DEFAULT_SESSION_TIMEOUT: u64 = 600;
-/// The beginning of the path where the session token will be written
-pub(crate) static SESSION_PATH: &str = "/run/rudo/";
-/// The default path of the configuration file
-pub(crate) static CONFIG_PATH: &str = "/etc/rudo.conf";
+/// Main function of the program.
+/// It's the only place where Rudo exit, with the exit code that correspond to the error
+#[allow(clippy::exit)]
+fn main() {
+    if let Err(err) = rudo() {
+        eprintln!("Error: {}", err);
+        process::exit(error::exit_code(err.as_ref()));
+    }
+}
 
-/// Main function of the program
-fn main() -> Result<(), Box<dyn Error>> {
+/// Function that initialize the logs and run Rudo
+fn rudo() -> Result<(), Box<dyn Error>> {
     // Initialize the CLI interface with clap
     let matches = cli::init_command_line();
 
@@ -128,11 +127,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     #[cfg(all(target_os = "linux", feature = "journald"))]
     // Use journald for logging
-    journal::log_journald(debug)?;
+    rudo_core::log_journald(debug)?;
 
     #[cfg(all(target_os = "macos", feature = "macos"))]
     // Use oslog for logging
-    journal::log_oslog(debug)?;
+    rudo_core::log_oslog(debug)?;
 
     // Principal function of Rudo
     run::run(&matches)?;
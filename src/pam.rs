@@ -0,0 +1,310 @@
+/*    Rudo is a program to get privilege access on unix system
+ *    Copyright (C) 2021  Rémi Lauzier <remilauzier@protonmail.com>
+ *
+ *    This program is free software; you can redistribute it and/or modify
+ *    it under the terms of the GNU General Public License as published by
+ *    the Free Software Foundation; either version 2 of the License, or
+ *    (at your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful,
+ *    but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *    GNU General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License along
+ *    with this program; if not, write to the Free Software Foundation, Inc.,
+ *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+ */
+use std::{
+    convert::TryFrom,
+    error::Error,
+    ffi::{CStr, CString, OsStr, OsString},
+    mem::size_of,
+    os::{
+        raw::{c_char, c_int, c_void},
+        unix::ffi::OsStrExt,
+    },
+    ptr, slice,
+};
+
+use libc::{calloc, free, strdup};
+use pam_client::{ConversationHandler, Flag, ReturnCode};
+use pam_sys::{
+    types::{PamConversation, PamHandle, PamItemType, PamMessage, PamMessageStyle, PamResponse},
+    wrapped::{end, set_item, start},
+};
+
+use crate::conv::Conversation;
+
+/// The value of `PAM_SUCCESS` that the conversation give back to Pam
+const CONV_SUCCESS: c_int = 0;
+/// The value of `PAM_BUF_ERR` that the conversation give back to Pam
+const CONV_BUF_ERR: c_int = 5;
+/// The value of `PAM_CONV_ERR` that the conversation give back to Pam
+const CONV_ERR: c_int = 19;
+
+extern "C" {
+    /// Function of Pam that give a copy of its environment. `pam_sys` declare it constant,
+    /// but the list and its variables belong to the caller, that must free them
+    fn pam_getenvlist(pamh: *mut PamHandle) -> *mut *mut c_char;
+}
+
+/// `Transaction` is a transaction of Pam, from `pam_start` to `pam_end`.
+/// Unlike `pam_client`, each call of Pam is made alone, so the one that failed is known
+pub(crate) struct Transaction {
+    /// The handle of Pam, that is never null
+    handle: *mut PamHandle,
+    /// The conversation, in a box so it stays at the address given to Pam
+    conversation: Box<Conversation>,
+    /// The result of the last call, that is given to `pam_end`
+    last_status: ReturnCode,
+}
+
+impl Transaction {
+    /// Start the transaction of Pam for `service` to authenticate `user`, the questions are asked by `conversation`
+    pub(crate) fn new(
+        service: &str,
+        user: &str,
+        conversation: Conversation,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut boxed = Box::new(conversation);
+        let data: *mut Conversation = &mut *boxed;
+        // Pam copy the structure, only the conversation must outlive the call
+        let pam_conv = PamConversation {
+            conv: Some(converse),
+            data_ptr: data.cast::<c_void>(),
+        };
+        let mut handle: *mut PamHandle = ptr::null_mut();
+        let status = start(service, Some(user), &pam_conv, &mut handle);
+        if status != ReturnCode::SUCCESS || handle.is_null() {
+            return Err(From::from(format!(
+                "Couldn't start Pam for the service {}: {:?}",
+                service, status
+            )));
+        }
+        Ok(Self {
+            handle,
+            conversation: boxed,
+            last_status: ReturnCode::SUCCESS,
+        })
+    }
+    /// Give the conversation, to change how it asks the questions
+    pub(crate) fn conversation_mut(&mut self) -> &mut Conversation {
+        &mut self.conversation
+    }
+    /// Make a call of Pam that only take flags, like `pam_authenticate` or `pam_setcred`
+    pub(crate) fn call(
+        &mut self,
+        function: fn(&mut PamHandle, Flag) -> ReturnCode,
+        flags: Flag,
+    ) -> Result<(), pam_client::Error> {
+        // The handle is valid from pam_start to pam_end
+        let status = function(unsafe { &mut *self.handle }, flags);
+        self.result(status)
+    }
+    /// Change the user of the transaction
+    pub(crate) fn set_user(&mut self, user: &str) -> Result<(), Box<dyn Error>> {
+        let name = CString::new(user)?;
+        // Pam copy the name, it doesn't need to outlive the call
+        let item = unsafe { &*name.as_ptr().cast::<c_void>() };
+        let status = set_item(unsafe { &mut *self.handle }, PamItemType::USER, item);
+        Ok(self.result(status)?)
+    }
+    /// Give the environment variables of Pam, as pairs of name and value
+    pub(crate) fn env_list(&self) -> Vec<(OsString, OsString)> {
+        let mut variables = Vec::new();
+        let list = unsafe { pam_getenvlist(self.handle) };
+        if list.is_null() {
+            return variables;
+        }
+        let mut index: usize = 0;
+        loop {
+            // The list end with a null pointer
+            let entry = unsafe { *list.add(index) };
+            if entry.is_null() {
+                break;
+            }
+            let bytes = unsafe { CStr::from_ptr(entry) }.to_bytes();
+            if let Some(equal) = bytes.iter().position(|&byte| byte == b'=') {
+                let name = bytes.get(..equal).unwrap_or_default();
+                let value = bytes.get(equal.saturating_add(1)..).unwrap_or_default();
+                variables.push((
+                    OsStr::from_bytes(name).to_owned(),
+                    OsStr::from_bytes(value).to_owned(),
+                ));
+            }
+            unsafe { free(entry.cast::<c_void>()) };
+            index = index.saturating_add(1);
+        }
+        unsafe { free(list.cast::<c_void>()) };
+        variables
+    }
+    /// Keep the status of a call for `pam_end`, and transform it in a result
+    fn result(&mut self, status: ReturnCode) -> Result<(), pam_client::Error> {
+        self.last_status = status;
+        if status == ReturnCode::SUCCESS {
+            Ok(())
+        } else {
+            Err(pam_client::Error::new(unsafe { &mut *self.handle }, status))
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        end(unsafe { &mut *self.handle }, self.last_status);
+    }
+}
+
+/// Function that Pam call to ask its questions, they are passed to the conversation of the transaction.
+/// The answers are allocated by the C library, as Pam free them
+extern "C" fn converse(
+    num_msg: c_int,
+    msg: *mut *mut PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut c_void,
+) -> c_int {
+    let count = match usize::try_from(num_msg) {
+        Ok(count) if count > 0 => count,
+        _ => return CONV_BUF_ERR,
+    };
+    if msg.is_null() || resp.is_null() || appdata_ptr.is_null() {
+        return CONV_BUF_ERR;
+    }
+    // The data is the conversation of the transaction, that live until pam_end
+    let conversation = unsafe { &mut *appdata_ptr.cast::<Conversation>() };
+    let answers = unsafe { calloc(count, size_of::<PamResponse>()) }.cast::<PamResponse>();
+    if answers.is_null() {
+        return CONV_BUF_ERR;
+    }
+    // Pam give an array of pointers to the messages, with one answer for each of them
+    let messages = unsafe { slice::from_raw_parts(msg, count) };
+    let responses = unsafe { slice::from_raw_parts_mut(answers, count) };
+    for (&message, response) in messages.iter().zip(responses.iter_mut()) {
+        let result =
+            answer(conversation, unsafe { message.as_ref() }).and_then(|text| match text {
+                Some(line) => {
+                    response.resp = unsafe { strdup(line.as_ptr()) };
+                    if response.resp.is_null() {
+                        Err(CONV_BUF_ERR)
+                    } else {
+                        Ok(())
+                    }
+                }
+                None => Ok(()),
+            });
+        if let Err(code) = result {
+            free_answers(responses);
+            unsafe { free(answers.cast::<c_void>()) };
+            return code;
+        }
+    }
+    unsafe { *resp = answers };
+    CONV_SUCCESS
+}
+
+/// Function to ask a message of Pam to the conversation, only the prompts have an answer
+fn answer(
+    conversation: &mut Conversation,
+    message: Option<&PamMessage>,
+) -> Result<Option<CString>, c_int> {
+    let pam_message = message.ok_or(CONV_BUF_ERR)?;
+    let text = if pam_message.msg.is_null() {
+        <&CStr>::default()
+    } else {
+        unsafe { CStr::from_ptr(pam_message.msg) }
+    };
+    match PamMessageStyle::from(pam_message.msg_style) {
+        PamMessageStyle::PROMPT_ECHO_ON => conversation
+            .prompt_echo_on(text)
+            .map(Some)
+            .map_err(|_| CONV_ERR),
+        PamMessageStyle::PROMPT_ECHO_OFF => conversation
+            .prompt_echo_off(text)
+            .map(Some)
+            .map_err(|_| CONV_ERR),
+        PamMessageStyle::TEXT_INFO => {
+            conversation.text_info(text);
+            Ok(None)
+        }
+        PamMessageStyle::ERROR_MSG => {
+            conversation.error_msg(text);
+            Ok(None)
+        }
+    }
+}
+
+/// Function to free the answers that were given, when the conversation fail
+fn free_answers(responses: &mut [PamResponse]) {
+    for response in responses {
+        if !response.resp.is_null() {
+            unsafe { free(response.resp.cast::<c_void>()) };
+            response.resp = ptr::null_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, ffi::CString, ptr};
+
+    use std::os::raw::c_void;
+
+    use libc::free;
+    use pam_sys::types::{PamMessage, PamResponse};
+
+    use super::{converse, free_answers, CONV_BUF_ERR, CONV_SUCCESS};
+    use crate::conv::{Conversation, PasswordSource};
+
+    #[test]
+    fn test_converse() -> Result<(), Box<dyn Error>> {
+        // The helper echo its prompt, so the answer is the prompt that was asked
+        let mut conversation =
+            Conversation::new(PasswordSource::Askpass(String::from("echo")), None)?;
+        let data: *mut Conversation = &mut conversation;
+        let prompt = CString::new("Password: ")?;
+        let info = CString::new("Hello")?;
+        let mut first = PamMessage {
+            msg_style: 1,
+            msg: prompt.as_ptr(),
+        };
+        let mut second = PamMessage {
+            msg_style: 4,
+            msg: info.as_ptr(),
+        };
+        let first_message: *mut PamMessage = &mut first;
+        let mut messages = [first_message, &mut second];
+        let mut answers: *mut PamResponse = ptr::null_mut();
+        let code = converse(
+            2,
+            messages.as_mut_ptr(),
+            &mut answers,
+            data.cast::<c_void>(),
+        );
+        let empty = converse(
+            0,
+            messages.as_mut_ptr(),
+            &mut answers,
+            data.cast::<c_void>(),
+        );
+        if answers.is_null() {
+            return Err(From::from("Test failed: the conversation gave no answer"));
+        }
+        let responses = unsafe { std::slice::from_raw_parts_mut(answers, 2) };
+        let password = responses
+            .first()
+            .map(|response| unsafe { CString::from(std::ffi::CStr::from_ptr(response.resp)) });
+        let no_answer = responses.get(1).map(|response| response.resp.is_null());
+        free_answers(responses);
+        unsafe { free(answers.cast::<c_void>()) };
+        if code == CONV_SUCCESS
+            && empty == CONV_BUF_ERR
+            && password == Some(prompt)
+            && no_answer == Some(true)
+        {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to answer the messages of Pam"))
+        }
+    }
+}
@@ -15,33 +15,76 @@
  *    with this program; if not, write to the Free Software Foundation, Inc.,
  *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
  */
-use std::error::Error;
+use std::{error::Error, thread};
 
-use log::{debug, error};
-use pam_client::{conv_cli::Conversation, Context, Flag};
+use log::{debug, error, info};
+use rudo_core::{
+    audit_event,
+    config::{PasswordConf, RudoConf},
+    error::RudoError,
+};
+
+use crate::{
+    authenticator::{AuthFailure, Authenticator},
+    lockout,
+};
 
 /// `Password_input` is a function that ask the user for their password.
-/// Pam validates the password
+/// The backend validates the password of the account that `auth_as` choose, that is not always the one of the user.
+/// Each attempt is counted for the lockout before it's verified, and they are forgotten when the password is valid
 pub(crate) fn password_input(
-    password: bool,
-    context: &mut Context<Conversation>,
+    password_conf: &PasswordConf,
+    authenticator: &mut dyn Authenticator,
+    username: &str,
+    rudoconf: &RudoConf,
 ) -> Result<(), Box<dyn Error>> {
     // Don't ask for password if false in the configuration
-    if password {
-        // Authenticate the user (ask for password, 2nd-factor token, fingerprint, etc.)
-        debug!("Password will be asked a maximum of 3 time to the user");
-        let mut count: u8 = 0;
-        while count < 3 {
-            if let Ok(()) = context.authenticate(Flag::DISALLOW_NULL_AUTHTOK) {
+    if !password_conf.required {
+        return Ok(());
+    }
+    let account = password_conf.auth_as.account(username, &rudoconf.impuser);
+    // Authenticate the user (ask for password, 2nd-factor token, fingerprint, etc.)
+    debug!(
+        "Password will be asked a maximum of {} time to the user",
+        password_conf.tries
+    );
+    let mut count: u8 = 0;
+    while count < password_conf.tries {
+        count = count.saturating_add(1);
+        let failures = lockout::record_attempt(username, rudoconf)?;
+        match authenticator.authenticate() {
+            Ok(()) => return lockout::forget(username, rudoconf),
+            Err(AuthFailure::MaxTries) => {
+                // Pam has its own limit, asking again would only be refused
+                info!("Pam refuses more attempts for {}", username);
+                lockout::attempt_failed(username, rudoconf, failures);
                 break;
             }
-            error!("Password was incorrect! Will be report to administrator!");
-            eprintln!("Password was incorrect! Will be report to administrator!");
-            count = count.saturating_add(1);
-        }
-        if count == 3 {
-            return Err(From::from("You have made three mistake! Rudo Out!"));
+            Err(_) => {
+                lockout::attempt_failed(username, rudoconf, failures);
+                error!("{}", password_conf.badpass_message);
+                eprintln!("{}", password_conf.badpass_message);
+                if count < password_conf.tries {
+                    thread::sleep(password_conf.delay);
+                }
+            }
         }
     }
-    Ok(())
+    audit_event(
+        &format!(
+            "{} failed to authenticate as {} after {} attempts",
+            username, account, count
+        ),
+        &[
+            ("RUDO_EVENT", "authentication_failure"),
+            ("RUDO_USER", username),
+            ("RUDO_AUTH_AS", &password_conf.auth_as.to_string()),
+            ("RUDO_AUTH_USER", account),
+            ("RUDO_ATTEMPTS", &count.to_string()),
+        ],
+    );
+    Err(Box::new(RudoError::AuthFailed(format!(
+        "You have made {} mistake! Rudo Out!",
+        count
+    ))))
 }
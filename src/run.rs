@@ -15,38 +15,53 @@
  *    with this program; if not, write to the Free Software Foundation, Inc.,
  *    51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
  */
-use std::{env, error::Error};
+use std::{env, error::Error, time::SystemTime};
 
 use clap::ArgMatches;
-use log::{debug, info};
-use pam_client::{conv_cli::Conversation, Flag, Session};
+use log::{debug, error, info, warn};
+use rudo_core::{
+    cmd::{self, CmdData},
+    config::{self, Validity},
+    host::Host,
+    policy, session, user, CONFIG_PATH,
+};
 
-use crate::{auth, cmd, cmd::CmdData, config, user, utils};
+use crate::{
+    auth,
+    authenticator::{Authenticator, PamAuthenticator},
+    check,
+    conv::{Conversation, PasswordSource},
+    lockout,
+};
 
 /// Run function of Rudo.
 /// It takes the result of the command-line interface to decide
 /// if it will create a login shell or to pass a command or to invoke the editor
 pub(crate) fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
+    // Remove the stale session tokens and stop there if the user ask for it
+    if matches.is_present("gc") {
+        debug!("Removing the stale session tokens of every user");
+        let count = session::collect_garbage()?;
+        println!("{} tokens removed", count);
+        return Ok(());
+    }
+    // Unlock the user and stop there if root ask for it
+    if let Some(username) = matches.value_of("unlock") {
+        debug!("Unlocking {}", username);
+        lockout::unlock(username)?;
+        println!("{} was unlocked", username);
+        return Ok(());
+    }
+
     // Initialize configuration
     debug!("Starting configuration initialization");
-    let mut conf = config::init_conf()?;
+    let mut conf = load_config(matches)?;
 
     // Create the user data for later use
     debug!("Starting extraction of User information");
     let userdata = user::User::new()?;
 
-    // Extract the information from rudo.conf that is tie to the actual user
-    debug!(
-        "Starting extraction of the vector of UserConf tie to {} in rudo.conf",
-        &userdata.username
-    );
-    let mut userconf = config::extract_userconf(conf.user.clone(), &userdata.username);
-
     // Update configuration if necessary, as CLI as the priority
-    if matches.is_present("greeting") {
-        debug!("Update configuration with CLI option as it as the priority");
-        userconf = config::UserConf::update_greeting(userconf);
-    }
     if matches.is_present("user") {
         let impuser = match matches.value_of("user") {
             Some(user) => user.to_owned(),
@@ -55,6 +70,18 @@ pub(crate) fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
         conf = config::Config::update_user(conf, impuser);
     }
 
+    // Print the user entries of rudo.conf and stop there if root ask for it
+    if matches.is_present("check-config") {
+        debug!("Checking the user entries of rudo.conf");
+        return check::check_config(&conf, check::config_path(matches)?);
+    }
+
+    // Print the decision of the policy and stop there if the user ask for it
+    if matches.is_present("check") {
+        debug!("Checking the request against the policy of rudo.conf");
+        return check::check(matches, &conf, &userdata);
+    }
+
     // Get the UID and GID of the impersonated user for further use
     debug!(
         "Extract UID and GID of the impersonated user {}",
@@ -65,6 +92,28 @@ pub(crate) fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
         None => return Err(From::from("Please give Rudo a real unix username")),
     };
 
+    // Ask the policy of rudo.conf if the request of the user is allowed, before using Pam
+    debug!(
+        "Evaluate the request of {} with the policy of rudo.conf",
+        userdata.username
+    );
+    let request = request(matches, &conf, &userdata)?;
+    let decision = policy::evaluate(&conf, &request);
+    log_inactive(&decision, &request.user, CONFIG_PATH);
+
+    // The rule the policy chose for the user, an expired or pending one is never chosen
+    let mut userconf = decision
+        .rule
+        .and_then(|index| conf.user.get(index))
+        .cloned()
+        .unwrap_or_default();
+
+    // Update configuration if necessary, as CLI as the priority
+    if matches.is_present("greeting") {
+        debug!("Update configuration with CLI option as it as the priority");
+        userconf = config::UserConf::update_greeting(userconf);
+    }
+
     // Greet the user if the configuration said so
     if userconf.greeting {
         debug!("Start user greeting messages and disclaimer");
@@ -73,38 +122,158 @@ pub(crate) fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
             userdata.username
         );
     }
-
-    // Authenticate the user with the list of authorized user and group
-    debug!(
-        "Authenticate {} with the list in rudo.conf",
-        userdata.username
-    );
-    auth::authentification(&userconf, &userdata)?;
+    if let Err(err) = decision.enforce() {
+        error!("{}", decision.reason);
+        return Err(Box::new(err));
+    }
+    debug!("{}", decision.reason);
+    let policy::Decision {
+        auth,
+        env: env_rules,
+        digest,
+        ..
+    } = decision;
 
     // Create the Pam context and authenticate the user with Pam
     debug!(
         "Pam context initialization and identification of {}",
         userdata.username
     );
-    let mut context = auth::authentification_pam(&conf, &userconf, &userdata)?;
+    let options = auth_options(matches)?;
+    let mut authenticator = auth::authentification_pam(&conf, &auth, &userdata.username, options)?;
 
-    // Open session with Pam credentials
-    debug!("Session initialize with Pam credential");
-    let session = context.open_session(Flag::NONE)?;
+    // Log the user, and it's command for further audit by system administrator
+    info!(
+        "{} has been authorized. Command: {}",
+        userdata.username,
+        policy::command_line(&request)
+    );
 
-    // Run the command the user as choose
+    // Run the command the user as choose, inside the session of Pam
     debug!("Run the command {} as choose", userdata.username);
-    run_command(matches, &session, &impuser, &userdata)?;
+    let data = CmdData {
+        program: request.command.clone(),
+        args: request.args.iter().map(String::as_str).collect(),
+        digest,
+    };
+    run_in_session(&mut authenticator, |backend| {
+        cmd::start_command(data, backend.env_list(), &env_rules, &impuser)
+    })
+}
 
-    Ok(())
+/// Function to read the configuration, a check only read it and never create it
+fn load_config(matches: &ArgMatches<'_>) -> Result<config::Config, Box<dyn Error>> {
+    if matches.is_present("check") || matches.is_present("check-config") {
+        check::read_config(matches)
+    } else if matches.is_present("config") {
+        Err(From::from(
+            "--config can only be used with --check or --check-config",
+        ))
+    } else {
+        config::init_conf()
+    }
+}
+
+/// `run_in_session` establish the credentials and open the session, run the command, then close the session
+/// and delete the credentials. The session is closed even if the command failed
+fn run_in_session<F>(
+    authenticator: &mut dyn Authenticator,
+    command: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&dyn Authenticator) -> Result<(), Box<dyn Error>>,
+{
+    debug!("Establishing the credentials and opening the session");
+    authenticator.open_session()?;
+
+    let result = command(authenticator);
+
+    debug!("Closing the session and deleting the credentials");
+    let closed = authenticator.close_session();
+    result?;
+    closed
+}
+
+/// `password_source` extract from the command-line where the password must be read
+fn password_source(matches: &ArgMatches<'_>) -> Result<PasswordSource, Box<dyn Error>> {
+    if matches.is_present("stdin") {
+        Ok(PasswordSource::Stdin)
+    } else if matches.is_present("askpass") {
+        match env::var("RUDO_ASKPASS") {
+            Ok(program) if !program.is_empty() => Ok(PasswordSource::Askpass(program)),
+            _ => Err(From::from(
+                "RUDO_ASKPASS must give the helper program to use with --askpass",
+            )),
+        }
+    } else {
+        Ok(PasswordSource::Terminal)
+    }
 }
-/// `run_command` is a function that run the precise command the user demand
-fn run_command(
+
+/// The function that create the Pam context of the authentication
+type PamBackend = fn(&str, &str, Conversation) -> Result<PamAuthenticator, Box<dyn Error>>;
+
+/// Function to gather the options of the command-line that change how the user is authenticated, with Pam as backend
+fn auth_options(matches: &ArgMatches<'_>) -> Result<auth::AuthOptions<PamBackend>, Box<dyn Error>> {
+    let password_source = password_source(matches)?;
+    Ok(auth::AuthOptions {
+        non_interactive: matches.is_present("non-interactive"),
+        terminal: auth::find_terminal(&password_source)?,
+        password_source,
+        prompt: matches.value_of("prompt").map(str::to_owned),
+        login: matches.is_present("shell"),
+        backend: PamAuthenticator::new,
+    })
+}
+
+/// The program the user demand, and its arguments
+type CommandLine = (String, Vec<String>);
+
+/// Function to log the rules of the user that the policy ignored, as they expired or aren't in effect yet.
+/// `path` is the configuration file the rules come from
+pub(crate) fn log_inactive(decision: &policy::Decision, username: &str, path: &str) {
+    for &(index, validity) in &decision.inactive {
+        if validity == Validity::Expired {
+            warn!(
+                "The user entry {} of {} for {} expired, it's ignored",
+                index, path, username
+            );
+        } else {
+            info!(
+                "The user entry {} of {} for {} isn't in effect yet, it's ignored",
+                index, path, username
+            );
+        }
+    }
+}
+
+/// `request` is a function that gather everything the policy needs to know about the request of the user
+pub(crate) fn request(
     matches: &ArgMatches<'_>,
-    session: &Session<'_, Conversation>,
-    impuser: &users::User,
+    conf: &config::Config,
     userdata: &user::User,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<policy::Request, Box<dyn Error>> {
+    let (program, args) = requested_command(matches)?;
+    // The policy match the absolute path of the program, and it's that path that is run
+    let cwd = env::current_dir()?;
+    let command = cmd::resolve_program(&program, env::var_os("PATH").as_deref(), &cwd)?;
+    // The FQDN can query the DNS, it's only resolved when a rule is restricted to some hosts
+    let resolve_fqdn = conf.user.iter().any(|userconf| !userconf.hosts.is_empty());
+    Ok(policy::Request {
+        user: userdata.username.clone(),
+        groups: userdata.group_names(),
+        host: Host::local(resolve_fqdn)?,
+        target: conf.rudo.impuser.clone(),
+        command,
+        args,
+        cwd,
+        time: SystemTime::now(),
+    })
+}
+
+/// `requested_command` is a function that extract the program and its arguments the user demand,
+/// a command, a login shell or the editor
+fn requested_command(matches: &ArgMatches<'_>) -> Result<CommandLine, Box<dyn Error>> {
     // Verify the option the user as pass and act accordingly
     if matches.is_present("command") {
         // Extract the command in two part. First the name of the program then it's arguments.
@@ -118,30 +287,15 @@ fn run_command(
             }
         };
         let data = cmd::CmdData::new(command)?;
-        let args = utils::vec_to_string(data.args.clone());
-
-        // Log the user, and it's command for further audit by system administrator
-        info!(
-            "{} has been authorized. Command: {} {}",
-            userdata.username, data.program, args
-        );
-
-        cmd::start_command(data, session, impuser)?;
+        let args = data.args.iter().map(|&arg| arg.to_owned()).collect();
+        Ok((data.program, args))
     } else if matches.is_present("shell") {
         // Extraction of the shell environment variable
         debug!("Extracting shell environment variable");
         let shell = env::var("SHELL").unwrap_or_else(|_| String::from("/bin/sh"));
 
-        // Log the user, and it's shell for further audit by system administrator
-        info!("{} has been authorized to use {}", userdata.username, shell);
-
         // Pass the name of the shell, and the arguments "-l" to have a login shell
-        let data = CmdData {
-            program: shell,
-            args: vec!["-l"],
-        };
-
-        cmd::start_command(data, session, impuser)?;
+        Ok((shell, vec![String::from("-l")]))
     } else if matches.is_present("edit") {
         // Extraction of the editor environment variable
         debug!("Extracting editor environment variable");
@@ -153,23 +307,34 @@ fn run_command(
             Some(arg) => arg,
             None => return Err(From::from("Error couldn't pass value of edit to a &str")),
         };
+        Ok((editor, vec![arg.to_owned()]))
+    } else {
+        Err(From::from(
+            "You shouldn't be able to see this error. CLI should have stopped you",
+        ))
+    }
+}
 
-        // Log the user, it's editor, and it's arguments for further audit by system administrator
-        info!(
-            "{} has been authorized to use {} {}",
-            userdata.username, editor, arg
-        );
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
 
-        let data = CmdData {
-            program: editor,
-            args: vec![arg],
-        };
+    use super::run_in_session;
+    use crate::authenticator::FakeAuthenticator;
 
-        cmd::start_command(data, session, impuser)?;
-    } else {
-        return Err(From::from(
-            "You shouldn't be able to see this error. CLI should have stopped you",
-        ));
+    #[test]
+    fn test_run_in_session() -> Result<(), Box<dyn Error>> {
+        let mut fake = FakeAuthenticator::new("alice", "secret", &[]);
+        let mut opened = false;
+        run_in_session(&mut fake, |backend| {
+            opened = !backend.env_list().is_empty();
+            Ok(())
+        })?;
+        let result = run_in_session(&mut fake, |_| Err(From::from("Command failed")));
+        if opened && result.is_err() && !fake.session_open {
+            Ok(())
+        } else {
+            Err(From::from("Test failed to close the session"))
+        }
     }
-    Ok(())
 }